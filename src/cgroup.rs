@@ -0,0 +1,348 @@
+use crate::data::{ContainerSample, MemoryBreakdown};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Instant
+};
+
+/// Where the cgroup hierarchy is usually mounted.
+pub const DEFAULT_ROOT: &str = "/sys/fs/cgroup";
+
+/// Where Docker keeps each container's config, named by ID.
+pub const DOCKER_CONTAINERS: &str = "/var/lib/docker/containers";
+
+/// Prefixes container runtimes put in front of the container ID in cgroup names.
+const PREFIXES: [&str; 5] = ["docker-", "cri-containerd-", "crio-", "libpod-", "containerd-"];
+
+/// Names the v1 `cpuacct` controller is mounted under, alone or co-mounted with `cpu`.
+const CPUACCT: [&str; 3] = ["cpuacct", "cpu,cpuacct", "cpuacct,cpu"];
+
+/// How deep to look for container cgroups, kubepods nest them a few levels down.
+const MAX_DEPTH: usize = 6;
+
+/// A container's cgroup directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerCgroup {
+    pub id: String,
+    pub path: PathBuf
+}
+
+/// Finds container cgroups (`system.slice/docker-<id>.scope`, `docker/<id>`, `kubepods/.../<id>`, ...) under `root`.
+pub fn discover(root: &Path) -> Vec<ContainerCgroup> {
+    let mut found = Vec::new();
+    walk(root, 0, &mut found);
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    found
+}
+
+fn walk(dir: &Path, depth: usize, found: &mut Vec<ContainerCgroup>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+
+        match container_id(&entry.file_name().to_string_lossy()) {
+            // Anything nested in a container cgroup belongs to that container
+            Some(id) => found.push(ContainerCgroup { id, path }),
+            None if depth < MAX_DEPTH => walk(&path, depth + 1, found),
+            None => {}
+        }
+    }
+}
+
+/// Extracts the container ID from a cgroup directory name, if it looks like one.
+pub fn container_id(name: &str) -> Option<String> {
+    let name = name.strip_suffix(".scope").unwrap_or(name);
+    let id = PREFIXES
+        .iter()
+        .find_map(|p| name.strip_prefix(p))
+        .unwrap_or(name);
+
+    (id.len() == 64 && id.bytes().all(|b| b.is_ascii_hexdigit())).then(|| id.to_string())
+}
+
+/// The layout of the hierarchy mounted at the cgroup root.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Version {
+    /// One tree per controller (`cpuacct/`, `memory/`, `blkio/`, ...).
+    V1,
+    /// A single unified tree.
+    V2
+}
+
+/// Detects the hierarchy version, hybrid setups are read as v1 as that's where their controllers live.
+pub fn version(root: &Path) -> Version {
+    if root.join("cgroup.controllers").exists() {
+        Version::V2
+    } else {
+        Version::V1
+    }
+}
+
+/// Raw counters read from a container's cgroup.
+struct Counters {
+    cpu_ns: u64,
+    memory: (u64, u64),
+    /// Whether the memory limit is the container's own, not the host's memory.
+    limited: bool,
+    /// The CPU limit in CPUs.
+    cpu_limit: Option<f64>,
+    breakdown: Option<MemoryBreakdown>,
+    io: (u64, u64),
+    oom_kills: Option<u64>
+}
+
+/// Reads container stats straight from the cgroup hierarchy (v1 or v2), no runtime needed.
+pub struct CgroupReader {
+    root: PathBuf,
+    host_memory: u64,
+    previous: HashMap<String, (u64, Instant)>,
+    /// Where to look up container names, see [`DOCKER_CONTAINERS`].
+    configs: Option<PathBuf>,
+    names: HashMap<String, String>
+}
+
+impl CgroupReader {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            host_memory: host_memory().unwrap_or_default(),
+            previous: HashMap::new(),
+            configs: None,
+            names: HashMap::new()
+        }
+    }
+
+    /// Names containers after the `<id>/config.v2.json` files in a directory, rather than by their short ID.
+    pub fn with_configs(mut self, configs: impl Into<PathBuf>) -> Self {
+        self.configs = Some(configs.into());
+        self
+    }
+
+    pub fn root(&self) -> &Path { &self.root }
+
+    /// Whether there's a hierarchy to read containers from, a unified one or the v1 `cpuacct` controller.
+    pub fn readable(&self) -> bool { self.root.is_dir() && (version(&self.root) == Version::V2 || self.v1_controller(&CPUACCT).is_dir()) }
+
+    /// Samples every container cgroup, CPU usage is computed against the previous call.
+    pub fn read(&mut self) -> Vec<ContainerSample> { self.read_at(Instant::now()) }
+
+    /// Same as [`CgroupReader::read`], with an explicit sampling time.
+    pub fn read_at(&mut self, now: Instant) -> Vec<ContainerSample> {
+        let samples: Vec<(String, Counters)> = match version(&self.root) {
+            Version::V2 => discover(&self.root)
+                .into_iter()
+                .filter_map(|cgroup| Some((cgroup.id, self.read_v2(&cgroup.path)?)))
+                .collect(),
+            Version::V1 => {
+                // Containers are found through the cpuacct tree, then looked up in the other controllers
+                let cpuacct = self.v1_controller(&CPUACCT);
+                discover(&cpuacct)
+                    .into_iter()
+                    .filter_map(|cgroup| {
+                        let relative = cgroup.path.strip_prefix(&cpuacct).ok()?;
+                        Some((cgroup.id, self.read_v1(&cpuacct, relative)?))
+                    })
+                    .collect()
+            }
+        };
+
+        // Forget containers that went away
+        self.previous
+            .retain(|id, _| samples.iter().any(|(s, _)| s == id));
+        self.names
+            .retain(|id, _| samples.iter().any(|(s, _)| s == id));
+
+        samples
+            .into_iter()
+            .map(|(id, counters)| self.sample(&id, counters, now))
+            .collect()
+    }
+
+    fn read_v2(&self, path: &Path) -> Option<Counters> {
+        let usage_usec = read_keyed(&path.join("cpu.stat")).and_then(|s| s.get("usage_usec").copied())?;
+
+        // Like docker, don't count the inactive page cache as used memory
+        let current = read_value(&path.join("memory.current")).unwrap_or_default();
+        let stat = read_keyed(&path.join("memory.stat"));
+        let inactive = stat.as_ref().and_then(|s| s.get("inactive_file").copied());
+        let max = read_value(&path.join("memory.max"));
+
+        // Swap has a file of its own
+        let breakdown = stat
+            .as_ref()
+            .and_then(MemoryBreakdown::from_stat)
+            .map(|b| MemoryBreakdown {
+                swap: read_value(&path.join("memory.swap.current")),
+                ..b
+            });
+
+        Some(Counters {
+            cpu_ns: usage_usec * 1000,
+            memory: (
+                current.saturating_sub(inactive.unwrap_or_default()),
+                max.unwrap_or(self.host_memory)
+            ),
+            limited: max.is_some(),
+            cpu_limit: read_cpu_max(&path.join("cpu.max")),
+            breakdown,
+            io: read_io_stat(&path.join("io.stat")),
+            oom_kills: read_keyed(&path.join("memory.events")).and_then(|s| s.get("oom_kill").copied())
+        })
+    }
+
+    fn read_v1(&self, cpuacct: &Path, relative: &Path) -> Option<Counters> {
+        let cpu_ns = read_value(&cpuacct.join(relative).join("cpuacct.usage"))?;
+
+        let memory = self.v1_controller(&["memory"]).join(relative);
+        let usage = read_value(&memory.join("memory.usage_in_bytes")).unwrap_or_default();
+        let stat = read_keyed(&memory.join("memory.stat"));
+        let inactive = stat
+            .as_ref()
+            .and_then(|s| s.get("total_inactive_file").copied());
+        let breakdown = stat
+            .as_ref()
+            .and_then(MemoryBreakdown::from_stat)
+            .map(|b| MemoryBreakdown {
+                kernel: read_value(&memory.join("memory.kmem.usage_in_bytes")),
+                ..b
+            });
+
+        // Unlimited containers report a huge page-aligned number instead of `max`
+        let (limit, limited) = match read_value(&memory.join("memory.limit_in_bytes")) {
+            Some(limit) if self.host_memory == 0 || limit < self.host_memory => (limit, true),
+            _ => (self.host_memory, false)
+        };
+
+        let blkio = self.v1_controller(&["blkio"]).join(relative);
+
+        Some(Counters {
+            cpu_ns,
+            memory: (usage.saturating_sub(inactive.unwrap_or_default()), limit),
+            limited,
+            cpu_limit: self.read_cfs_quota(relative),
+            breakdown,
+            io: read_blkio_service_bytes(&blkio.join("blkio.throttle.io_service_bytes")),
+            // Only kernels from 4.13 on count kills here
+            oom_kills: read_keyed(&memory.join("memory.oom_control")).and_then(|s| s.get("oom_kill").copied())
+        })
+    }
+
+    /// Reads the CPU limit out of a v1 cgroup's CFS quota, which is -1 without one.
+    fn read_cfs_quota(&self, relative: &Path) -> Option<f64> {
+        let cpu = self
+            .v1_controller(&["cpu", "cpu,cpuacct", "cpuacct,cpu"])
+            .join(relative);
+        let quota = read_value(&cpu.join("cpu.cfs_quota_us"))?;
+        let period = read_value(&cpu.join("cpu.cfs_period_us")).filter(|p| *p > 0)?;
+        Some(quota as f64 / period as f64)
+    }
+
+    /// Finds a v1 controller's mount, which may be co-mounted under a combined name.
+    fn v1_controller(&self, names: &[&str]) -> PathBuf {
+        names
+            .iter()
+            .map(|name| self.root.join(name))
+            .find(|path| path.is_dir())
+            .unwrap_or_else(|| self.root.join(names[0]))
+    }
+
+    fn sample(&mut self, id: &str, counters: Counters, now: Instant) -> ContainerSample {
+        let cpu_perc = match self.previous.insert(id.to_string(), (counters.cpu_ns, now)) {
+            Some((prev_ns, prev_time)) if now > prev_time => {
+                let elapsed = now.duration_since(prev_time).as_nanos() as f64;
+                counters.cpu_ns.saturating_sub(prev_ns) as f64 / elapsed * 100.0
+            }
+            _ => 0.0
+        };
+
+        // Docker's config is only readable as root, other containers go by their short ID
+        let configs = &self.configs;
+        let name = self.names.entry(id.to_string()).or_insert_with(|| {
+            configs
+                .as_ref()
+                .and_then(|dir| read_name(&dir.join(id).join("config.v2.json")))
+                .unwrap_or_else(|| id[..12].to_string())
+        });
+
+        let mut sample = ContainerSample::new(id, name, cpu_perc, counters.memory, (0, 0), counters.io);
+        sample.oom_kills = counters.oom_kills;
+        sample.mem_limited = Some(counters.limited);
+        sample.cpu_limit = counters.cpu_limit;
+        sample.mem_breakdown = counters.breakdown;
+        sample
+    }
+}
+
+/// Reads the container name out of Docker's `config.v2.json`, where it's stored with a leading slash.
+fn read_name(path: &Path) -> Option<String> {
+    let config: serde_json::Value = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    let name = config.get("Name")?.as_str()?.trim_start_matches('/');
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Reads a single numeric value, `max` and other non-numbers yield `None`.
+fn read_value(path: &Path) -> Option<u64> { fs::read_to_string(path).ok()?.trim().parse().ok() }
+
+/// Reads the CPU limit out of `cpu.max`, which holds `<quota> <period>` or `max <period>` without one.
+fn read_cpu_max(path: &Path) -> Option<f64> {
+    let content = fs::read_to_string(path).ok()?;
+    let (quota, period) = content.trim().split_once(' ')?;
+    let (quota, period) = (quota.parse::<u64>().ok()?, period.parse::<u64>().ok()?);
+    (period > 0).then(|| quota as f64 / period as f64)
+}
+
+/// Reads a flat keyed file like `cpu.stat` or `memory.stat`.
+fn read_keyed(path: &Path) -> Option<HashMap<String, u64>> {
+    let content = fs::read_to_string(path).ok()?;
+
+    Some(
+        content
+            .lines()
+            .filter_map(|line| {
+                let (key, value) = line.split_once(' ')?;
+                Some((key.to_string(), value.trim().parse().ok()?))
+            })
+            .collect()
+    )
+}
+
+/// Sums `rbytes` and `wbytes` over all devices in `io.stat`.
+fn read_io_stat(path: &Path) -> (u64, u64) {
+    let content = fs::read_to_string(path).unwrap_or_default();
+
+    content
+        .split_whitespace()
+        .filter_map(|field| field.split_once('='))
+        .fold((0, 0), |(read, write), (key, value)| match (key, value.parse::<u64>()) {
+            ("rbytes", Ok(v)) => (read + v, write),
+            ("wbytes", Ok(v)) => (read, write + v),
+            _ => (read, write)
+        })
+}
+
+/// Sums the `Read` and `Write` rows of v1's `blkio.throttle.io_service_bytes`.
+fn read_blkio_service_bytes(path: &Path) -> (u64, u64) {
+    let content = fs::read_to_string(path).unwrap_or_default();
+
+    content.lines().fold((0, 0), |(read, write), line| {
+        // Rows look like `8:0 Read 1234`, the last one is a grand `Total 1234`
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            [_, "Read", value] => (read + value.parse().unwrap_or(0), write),
+            [_, "Write", value] => (read, write + value.parse().unwrap_or(0)),
+            _ => (read, write)
+        }
+    })
+}
+
+/// Total host memory, used as the limit for containers without one.
+fn host_memory() -> Option<u64> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|l| l.starts_with("MemTotal:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
@@ -0,0 +1,221 @@
+use crate::{
+    state::{History, Marks},
+    utils::filler
+};
+use colored::{Color, Colorize};
+use std::time::{Duration, Instant};
+
+/// Braille dot bits, indexed by `[y][x]` within a 2x4 cell.
+const DOTS: [[u8; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// A grid of braille characters, each cell holding 2x4 dots.
+pub struct BrailleCanvas {
+    width: usize,
+    height: usize,
+    cells: Vec<u8>
+}
+
+impl BrailleCanvas {
+    /// Creates a canvas of `width` x `height` terminal cells.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![0; width * height]
+        }
+    }
+
+    /// Width in dots.
+    pub fn dot_width(&self) -> usize { self.width * 2 }
+
+    /// Height in dots.
+    pub fn dot_height(&self) -> usize { self.height * 4 }
+
+    /// Sets a dot, `(0, 0)` being the top-left corner. Dots outside the canvas are ignored.
+    pub fn set(&mut self, x: usize, y: usize) {
+        if x < self.dot_width() && y < self.dot_height() {
+            self.cells[(y / 4) * self.width + x / 2] |= DOTS[y % 4][x % 2];
+        }
+    }
+
+    /// Draws a straight line between two dots.
+    pub fn line(&mut self, (x0, y0): (usize, usize), (x1, y1): (usize, usize)) {
+        let steps = x0.abs_diff(x1).max(y0.abs_diff(y1)).max(1);
+
+        for step in 0..=steps {
+            let t = step as f64 / steps as f64;
+            let x = x0 as f64 + (x1 as f64 - x0 as f64) * t;
+            let y = y0 as f64 + (y1 as f64 - y0 as f64) * t;
+            self.set(x.round() as usize, y.round() as usize);
+        }
+    }
+
+    /// Whether any dot is set in the given cell.
+    pub fn is_set(&self, column: usize, row: usize) -> bool { self.cells[row * self.width + column] != 0 }
+
+    /// The character for the given cell.
+    pub fn char(&self, column: usize, row: usize) -> char {
+        char::from_u32(0x2800 + self.cells[row * self.width + column] as u32).unwrap_or(' ')
+    }
+}
+
+/// A line to plot, the newest value is drawn at the right edge.
+pub struct Series<'a> {
+    pub history: &'a History,
+    pub color: Color
+}
+
+impl<'a> Series<'a> {
+    pub fn new(history: &'a History, color: Color) -> Self { Self { history, color } }
+}
+
+/// Width of the Y-axis labels, including the axis itself.
+const LABEL_WIDTH: usize = 12;
+
+/// Renders series as a line chart of `width` x `height` cells, plus a time axis line underneath and the events marked below it.
+/// The X axis spans the whole history window, and ends with the newest sample.
+pub fn line_chart(series: &[Series], marks: &Marks, max: f64, width: usize, height: usize, label: &dyn Fn(f64) -> String) -> Vec<String> {
+    let plot_width = width.saturating_sub(LABEL_WIDTH).max(1);
+    let height = height.max(1);
+    let window = series
+        .first()
+        .map_or(Duration::ZERO, |s| s.history.window());
+    let axis = TimeAxis {
+        end: series.iter().filter_map(|s| s.history.latest()).max(),
+        window,
+        dot_width: plot_width * 2
+    };
+
+    let canvases: Vec<BrailleCanvas> = series
+        .iter()
+        .map(|s| plot(s.history, &axis, max, plot_width, height))
+        .collect();
+
+    let mut lines: Vec<String> = (0..height)
+        .map(|row| {
+            // Label the top, middle and bottom rows
+            let value = match row {
+                0 => Some(max),
+                r if height > 2 && r == height / 2 => Some(max * (height - 1 - r) as f64 / (height - 1) as f64),
+                r if r == height - 1 => Some(0.0),
+                _ => None
+            };
+            let (text, axis) = value.map_or((String::new(), "│"), |v| (label(v), "┤"));
+
+            // Where series overlap, the first one listed wins the cell's colour
+            let plot: String = (0..plot_width)
+                .map(|column| {
+                    let bits = canvases
+                        .iter()
+                        .fold(0u32, |bits, c| bits | (c.char(column, row) as u32 - 0x2800));
+                    let color = series
+                        .iter()
+                        .zip(&canvases)
+                        .find(|(_, c)| c.is_set(column, row))
+                        .map_or(Color::White, |(s, _)| s.color);
+
+                    char::from_u32(0x2800 + bits)
+                        .unwrap_or(' ')
+                        .to_string()
+                        .color(color)
+                        .to_string()
+                })
+                .collect();
+
+            format!("{text:>width$} {axis}{plot}", width = LABEL_WIDTH - 2)
+        })
+        .collect();
+
+    lines.push(time_axis(&axis, plot_width));
+    lines.push(event_marks(&axis, marks, plot_width));
+    lines
+}
+
+/// Where things that happened within the window land on the X axis.
+struct TimeAxis {
+    /// When the newest sample was taken, at the right edge.
+    end: Option<Instant>,
+    window: Duration,
+    dot_width: usize
+}
+
+impl TimeAxis {
+    /// The dot column of a moment, `None` if it's older than the window.
+    fn x(&self, at: Instant) -> Option<usize> {
+        let age = self.end?.saturating_duration_since(at).as_secs_f64();
+        let span = self.window.as_secs_f64();
+        if span == 0.0 || age > span {
+            return None;
+        }
+
+        let last = (self.dot_width - 1) as f64;
+        Some((last - age / span * last).round().max(0.0) as usize)
+    }
+}
+
+/// Plots a single history on its own canvas, each sample where it was taken within the window.
+fn plot(history: &History, axis: &TimeAxis, max: f64, width: usize, height: usize) -> BrailleCanvas {
+    let mut canvas = BrailleCanvas::new(width, height);
+    let dot_height = canvas.dot_height();
+
+    let points: Vec<(usize, usize)> = history
+        .points()
+        .filter_map(|(at, value)| {
+            let level = if max > 0.0 { (value / max).clamp(0.0, 1.0) } else { 0.0 };
+            let y = (dot_height - 1) as f64 * (1.0 - level);
+            Some((axis.x(at)?, y.round() as usize))
+        })
+        .collect();
+
+    match points[..] {
+        [] => {}
+        [point] => canvas.set(point.0, point.1),
+        _ => points
+            .windows(2)
+            .for_each(|pair| canvas.line(pair[0], pair[1]))
+    }
+
+    canvas
+}
+
+/// The bottom axis, labelled with how many seconds ago each end of the window is.
+fn time_axis(time: &TimeAxis, width: usize) -> String {
+    let seconds = time.window.as_secs();
+    let mut axis: Vec<char> = filler("─", width, 0).chars().collect();
+
+    let mut place = |text: &str, at: usize| {
+        let at = at.min(axis.len().saturating_sub(text.len()));
+        for (i, c) in text.chars().enumerate() {
+            if let Some(slot) = axis.get_mut(at + i) {
+                *slot = c;
+            }
+        }
+    };
+
+    place(&format!("-{seconds}s"), 0);
+    if width > 24 {
+        place(&format!("-{}s", seconds / 2), width / 2 - 2);
+    }
+    place("now", width);
+
+    format!("{} └{}", filler(" ", LABEL_WIDTH - 2, 0), axis.into_iter().collect::<String>())
+}
+
+/// The line under the axis, with events marked where they happened so they line up with the plotted samples.
+/// They get a line of their own to leave the axis labels readable.
+fn event_marks(time: &TimeAxis, marks: &Marks, width: usize) -> String {
+    let mut cells: Vec<String> = vec![" ".to_string(); width];
+
+    // Oldest first, so the newest of the events sharing a column is the one drawn.
+    // Events newer than the last sample go at the right edge.
+    for (at, kind) in marks.events() {
+        let Some(x) = time.x(at.min(time.end.unwrap_or(at))) else {
+            continue;
+        };
+        if let Some(slot) = cells.get_mut(x / 2) {
+            *slot = kind.symbol().to_string().color(kind.color()).to_string();
+        }
+    }
+
+    format!("{}  {}", filler(" ", LABEL_WIDTH - 2, 0), cells.concat())
+}
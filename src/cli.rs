@@ -1,4 +1,12 @@
-use clap::{arg, Command};
+use crate::{
+    display::MEM_SCALES,
+    filter::{Label, Pattern},
+    group::GroupBy,
+    sort::SORT_KEYS,
+    source::SOURCES,
+    state::CPU_MODES
+};
+use clap::{arg, ArgAction, Command};
 
 pub fn args() -> Command {
     Command::new("ds")
@@ -7,4 +15,59 @@ pub fn args() -> Command {
         .arg(arg!(<CONTAINER> ... "The container to show stats for.").required(false))
         .arg(arg!(-c - -compact "Enable a simpler, more compact view."))
         .arg(arg!(-f - -full "Enable a more detailed view."))
+        .arg(
+            arg!(--filter <PATTERN> "Only show containers whose name matches a glob, or a regex between slashes. The cgroup source only knows Docker's names, other containers go by their short ID.")
+                .value_parser(Pattern::parse)
+                .action(ArgAction::Append)
+        )
+        .arg(
+            arg!(--label <LABEL> "Only show containers with a label, as key or key=value.")
+                .value_parser(Label::parse)
+                .action(ArgAction::Append)
+        )
+        .arg(arg!(--image <IMAGE> "Only show containers running an image, with or without its tag.").action(ArgAction::Append))
+        .arg(
+            arg!(--exclude <PATTERN> "Hide containers whose name matches a glob, or a regex between slashes.")
+                .value_parser(Pattern::parse)
+                .action(ArgAction::Append)
+        )
+        .arg(arg!(-a - -all "Also show stopped containers, and count containers by state."))
+        .arg(arg!(--events "Show the latest container events in a panel under the list."))
+        .arg(arg!(--chart <CONTAINER> "Show full-screen charts for a single container."))
+        .arg(arg!(-i - -interactive "Select containers with the keyboard and open their details."))
+        .arg(
+            arg!(-s --source <SOURCE> "Where to read container stats from.")
+                .value_parser(SOURCES)
+                .default_value("auto")
+        )
+        .arg(
+            arg!(--sort <KEY> "What to order containers by, the busiest first for metrics.")
+                .value_parser(SORT_KEYS)
+                .default_value("name")
+        )
+        .arg(arg!(-r - -reverse "Reverse the sort order."))
+        .arg(
+            arg!(--"cpu-mode" <MODE> "What CPU percentages are relative to: a single core, every host core, or the container's CPU limit.")
+                .value_parser(CPU_MODES)
+                .default_value("core")
+        )
+        .arg(
+            arg!(--scale <SCALE> "What RAM bars measure against: each container's own limit (local), or the hungriest container (global).")
+                .value_parser(MEM_SCALES)
+                .default_value("local")
+        )
+        .arg(
+            arg!(--"group-by" <GROUPING> "Split containers into sections with summed stats: compose, image or label:<key>.")
+                .value_parser(GroupBy::parse)
+        )
+        .arg(
+            arg!(--history <SECONDS> "How many seconds of history to keep for the sparklines.")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("60")
+        )
+        .arg(
+            arg!(--grace <SECONDS> "How many seconds to keep showing containers that exited or stopped reporting.")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("30")
+        )
 }
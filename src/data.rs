@@ -1,18 +1,268 @@
-use serde::Deserialize;
+use byte_unit::Byte;
+use serde::{de::Error, Deserialize, Deserializer};
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, Instant}
+};
 
+/// A normalized stats sample, independent of the source it was collected from.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "DockerStats")]
+pub struct ContainerSample {
+    pub id: String,
+    pub name: String,
+    /// CPU usage as a percentage of a single core.
+    pub cpu_perc: f64,
+    pub mem_perc: f64,
+    pub mem_usage: u64,
+    pub mem_limit: u64,
+    pub net_rx: u64,
+    pub net_tx: u64,
+    pub block_read: u64,
+    pub block_write: u64,
+    /// How many times the kernel OOM-killed a process in the container, for sources reading cgroups.
+    pub oom_kills: Option<u64>,
+    /// Whether `mem_limit` is a limit of the container's own rather than the host's memory, for sources that can tell.
+    pub mem_limited: Option<bool>,
+    /// The CPU limit in CPUs, for sources reading cgroups.
+    pub cpu_limit: Option<f64>,
+    /// How many CPUs the host has, for sources that report it.
+    pub host_cpus: Option<u32>,
+    /// Where the memory goes, for sources that break it down.
+    pub mem_breakdown: Option<MemoryBreakdown>,
+    /// When the sample was received.
+    pub timestamp: Instant
+}
+
+impl ContainerSample {
+    /// Builds a sample from raw counters, deriving the memory percentage from usage and limit.
+    pub fn new(id: &str, name: &str, cpu_perc: f64, (usage, limit): (u64, u64), (rx, tx): (u64, u64), (read, write): (u64, u64)) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            cpu_perc,
+            mem_perc: if limit == 0 { 0.0 } else { usage as f64 / limit as f64 * 100.0 },
+            mem_usage: usage,
+            mem_limit: limit,
+            net_rx: rx,
+            net_tx: tx,
+            block_read: read,
+            block_write: write,
+            oom_kills: None,
+            mem_limited: None,
+            cpu_limit: None,
+            host_cpus: None,
+            mem_breakdown: None,
+            timestamp: Instant::now()
+        }
+    }
+
+    /// Whether the container is referenced by `query`, either by name or by (a prefix of) its ID.
+    pub fn matches(&self, query: &str) -> bool { self.name == query || (!query.is_empty() && self.id.starts_with(query)) }
+}
+
+/// What the runtime knows about a container besides its stats, refreshed less often than the samples.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    pub image: String,
+    pub labels: HashMap<String, String>,
+    /// `running`, `paused`, `restarting`...
+    pub state: String,
+    /// When the container last started, in seconds since the Unix epoch.
+    pub started_at: Option<u64>,
+    /// When the container last stopped, in seconds since the Unix epoch.
+    pub finished_at: Option<u64>,
+    /// What the container's process last exited with.
+    pub exit_code: i64,
+    /// The health check status, `None` without a health check.
+    pub health: Option<String>,
+    pub restarts: u64,
+    /// Whether the container's last exit was an OOM kill.
+    pub oom_killed: bool,
+    /// Ports published on the host.
+    pub ports: Vec<Port>,
+    /// Whether a memory limit is set, `None` until the container is inspected.
+    pub mem_limited: Option<bool>,
+    /// The CPU limit in CPUs, `None` without one.
+    pub cpu_limit: Option<f64>
+}
+
+impl Metadata {
+    /// How long the container has been up, as of `now` in seconds since the Unix epoch.
+    pub fn uptime(&self, now: u64) -> Option<Duration> {
+        self.started_at
+            .map(|t| Duration::from_secs(now.saturating_sub(t)))
+    }
+}
+
+/// A container's memory split by what it's used for, in bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryBreakdown {
+    /// Anonymous memory, the heap and stacks making up the RSS.
+    pub anon: u64,
+    /// The page cache.
+    pub file: u64,
+    /// Kernel data structures like slabs and stacks, `None` when the source can't tell.
+    pub kernel: Option<u64>,
+    /// `None` when the source can't tell.
+    pub swap: Option<u64>
+}
+
+impl MemoryBreakdown {
+    /// Reads a cgroup's `memory.stat`, in its v2 or v1 flavour. `None` when it has neither.
+    pub fn from_stat(stat: &HashMap<String, u64>) -> Option<Self> {
+        let get = |key: &str| stat.get(key).copied();
+
+        if let Some(anon) = get("anon") {
+            // Kernels before 5.18 only count the kernel memory piece by piece, and swap has a file of its own
+            let pieces: Vec<u64> = ["kernel_stack", "pagetables", "percpu", "sock", "slab", "vmalloc"]
+                .iter()
+                .filter_map(|key| get(key))
+                .collect();
+            return Some(Self {
+                anon,
+                file: get("file").unwrap_or_default(),
+                kernel: get("kernel").or((!pieces.is_empty()).then(|| pieces.iter().sum())),
+                swap: None
+            });
+        }
+
+        // v1 counts the whole hierarchy under `total_`, with the kernel memory in a file of its own
+        Some(Self {
+            anon: get("total_rss").or_else(|| get("rss"))?,
+            file: get("total_cache")
+                .or_else(|| get("cache"))
+                .unwrap_or_default(),
+            kernel: None,
+            // Only counted with swap accounting on
+            swap: get("total_swap").or_else(|| get("swap"))
+        })
+    }
+
+    /// The memory of every part known.
+    pub fn total(&self) -> u64 { self.anon + self.file + self.kernel.unwrap_or_default() + self.swap.unwrap_or_default() }
+}
+
+/// A container port published on the host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Port {
+    /// The host address, `None` when bound to every interface.
+    pub ip: Option<String>,
+    pub public: u16,
+    pub private: u16,
+    pub protocol: String
+}
+
+impl fmt::Display for Port {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ip) = &self.ip {
+            write!(f, "{ip}:")?;
+        }
+        write!(f, "{}→{}/{}", self.public, self.private, self.protocol)
+    }
+}
+
+/// A row of `docker stats --format json`.
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "PascalCase")]
 pub struct DockerStats {
-    #[serde(rename = "BlockIO")]
-    pub block_io: String,
-    #[serde(rename = "CPUPerc")]
-    pub cpu_perc: String,
+    #[serde(rename = "BlockIO", deserialize_with = "byte_pair")]
+    pub block_io: (u64, u64),
+    #[serde(rename = "CPUPerc", deserialize_with = "percent")]
+    pub cpu_perc: f64,
     #[serde(rename = "ID")]
-    #[allow(dead_code)]
     pub id: String,
-    pub mem_perc: String,
-    pub mem_usage: String,
+    #[serde(deserialize_with = "percent")]
+    pub mem_perc: f64,
+    #[serde(deserialize_with = "byte_pair")]
+    pub mem_usage: (u64, u64),
+    pub name: String,
+    #[serde(rename = "NetIO", deserialize_with = "byte_pair")]
+    pub net_io: (u64, u64)
+}
+
+impl From<DockerStats> for ContainerSample {
+    fn from(stats: DockerStats) -> Self {
+        Self {
+            mem_perc: stats.mem_perc,
+            ..ContainerSample::new(
+                &stats.id,
+                &stats.name,
+                stats.cpu_perc,
+                stats.mem_usage,
+                stats.net_io,
+                stats.block_io
+            )
+        }
+    }
+}
+
+/// A row of `podman stats --format json`, which uses lower-case keys.
+#[derive(Deserialize, Debug, Clone)]
+pub struct PodmanStats {
+    pub id: String,
     pub name: String,
-    #[serde(rename = "NetIO")]
-    pub net_io: String
+    #[serde(deserialize_with = "percent")]
+    pub cpu_percent: f64,
+    #[serde(deserialize_with = "byte_pair")]
+    pub mem_usage: (u64, u64),
+    #[serde(deserialize_with = "percent")]
+    pub mem_percent: f64,
+    #[serde(deserialize_with = "byte_pair")]
+    pub net_io: (u64, u64),
+    #[serde(deserialize_with = "byte_pair")]
+    pub block_io: (u64, u64)
+}
+
+impl From<PodmanStats> for ContainerSample {
+    fn from(stats: PodmanStats) -> Self {
+        Self {
+            mem_perc: stats.mem_percent,
+            ..ContainerSample::new(
+                &stats.id,
+                &stats.name,
+                stats.cpu_percent,
+                stats.mem_usage,
+                stats.net_io,
+                stats.block_io
+            )
+        }
+    }
+}
+
+/// Parses a percentage like `12.5%`, the CLIs print `--` while they have no data yet.
+pub fn parse_percent(value: &str) -> Result<f64, String> {
+    match value.trim() {
+        "--" => Ok(0.0),
+        value => value
+            .strip_suffix('%')
+            .and_then(|v| v.parse::<f64>().ok())
+            .ok_or_else(|| format!("invalid percentage: {value:?}"))
+    }
+}
+
+/// Parses a pair of sizes like `1.2GiB / 7.6GiB` or `3kB / 1kB`.
+pub fn parse_byte_pair(value: &str) -> Result<(u64, u64), String> {
+    let (left, right) = value
+        .split_once(" / ")
+        .ok_or_else(|| format!("invalid byte pair: {value:?}"))?;
+    Ok((parse_bytes(left)?, parse_bytes(right)?))
+}
+
+fn parse_bytes(value: &str) -> Result<u64, String> {
+    match value.trim() {
+        "--" => Ok(0),
+        value => Byte::parse_str(value, true)
+            .map(|b| b.as_u64())
+            .map_err(|e| format!("invalid size {value:?}: {e}"))
+    }
+}
+
+fn percent<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    parse_percent(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+}
+
+fn byte_pair<'de, D: Deserializer<'de>>(deserializer: D) -> Result<(u64, u64), D::Error> {
+    parse_byte_pair(&String::deserialize(deserializer)?).map_err(D::Error::custom)
 }
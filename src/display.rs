@@ -1,12 +1,144 @@
-use crate::{data::DockerStats, utils::*};
-use byte_unit::Byte;
-use colored::Colorize;
-use std::io::{self, Write};
+use crate::{
+    chart::{self, Series},
+    data::{MemoryBreakdown, Metadata, Port},
+    event::EventLog,
+    group::{self, Group, GroupBy, Totals},
+    sort::Sort,
+    state::{ContainerState, CpuMode, History},
+    utils::*
+};
+use byte_unit::UnitType;
+use colored::{Color, ColoredString, Colorize};
+use regex::Regex;
+use std::{
+    cell::Cell,
+    collections::HashSet,
+    io::{self, Write},
+    sync::{Arc, LazyLock, Mutex},
+    time::{Duration, Instant}
+};
+
+/// Below this width the bars no longer fit and each container is summarized on a single line.
+const MIN_WIDTH: usize = 24;
+
+/// How many events the panel under the list shows.
+const EVENT_ROWS: usize = 5;
+
+/// How long a recreated container is flagged for.
+const RECREATED_FOR: Duration = Duration::from_secs(60);
+
+/// The values accepted by `--scale`.
+pub const MEM_SCALES: [&str; 2] = ["global", "local"];
+
+/// What fills a container's RAM bar.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MemScale {
+    /// The memory used by the hungriest container, to compare them.
+    Global,
+    /// The container's own limit, or the host's memory without one.
+    #[default]
+    Local
+}
+
+impl MemScale {
+    pub fn parse(scale: &str) -> Option<Self> {
+        match scale {
+            "global" => Some(Self::Global),
+            "local" => Some(Self::Local),
+            _ => None
+        }
+    }
+}
 
 pub struct StatsDisplay {
     width: usize,
     compact: bool,
-    full: bool
+    full: bool,
+    chart: Option<String>,
+    height: usize,
+    /// Index of the first entry shown when they don't all fit.
+    offset: usize,
+    interactive: bool,
+    selected: Option<Selection>,
+    /// Whether the detail pane of the selected container is open.
+    detail: bool,
+    sort: Sort,
+    group_by: Option<GroupBy>,
+    /// Keys of the groups whose containers are hidden.
+    collapsed: HashSet<Option<String>>,
+    /// Set while drawing a stale or stopped container, whose lines are greyed out.
+    dim: Cell<bool>,
+    /// Whether to count the containers by state above the list.
+    summary: bool,
+    /// The log the event panel under the list shows, if any.
+    events: Option<Arc<Mutex<EventLog>>>,
+    cpu_mode: CpuMode,
+    mem_scale: MemScale
+}
+
+/// What the selection cursor is on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selection {
+    /// A container, by ID.
+    Container(String),
+    /// A group header, by key.
+    Group(Option<String>)
+}
+
+/// A line-up item of the container list.
+enum Entry<'a> {
+    Header(Group<'a>),
+    /// A container, with whether it opens and closes a run of boxes.
+    Container {
+        state: &'a ContainerState,
+        first: bool,
+        last: bool
+    }
+}
+
+impl Entry<'_> {
+    fn selection(&self) -> Selection {
+        match self {
+            Entry::Header(group) => Selection::Group(group.key.clone()),
+            Entry::Container { state, .. } => Selection::Container(state.id().to_string())
+        }
+    }
+
+    fn is_container(&self) -> bool { matches!(self, Entry::Container { .. }) }
+}
+
+/// The global scales shared by every container in a frame.
+struct Scale {
+    /// Percent that fills the CPU bars.
+    perc: f32,
+    /// Bytes that fill the RAM bars when they share a scale.
+    mem: f64,
+    /// Bytes per second that fill the NET bars.
+    net: f64,
+    /// Bytes per second that fill the IO bars.
+    io: f64
+}
+
+impl Scale {
+    /// The scales that fit every container, with their CPU usage as `cpu_mode` counts it.
+    fn of(containers: &[ContainerState], cpu_mode: CpuMode) -> Self {
+        let mut scale = Scale {
+            perc: 100.0,
+            mem: 0.0,
+            net: 0.0,
+            io: 0.0
+        };
+
+        for state in containers {
+            let (stats, rates) = (&state.sample, &state.rates);
+            scale.perc = scale.perc.max(cpu_mode.perc(state) as f32);
+            scale.mem = scale.mem.max(stats.mem_usage as f64);
+            scale.net = scale.net.max(rates.net_rx).max(rates.net_tx);
+            scale.io = scale.io.max(rates.block_read).max(rates.block_write);
+        }
+
+        scale
+    }
 }
 
 impl StatsDisplay {
@@ -14,11 +146,235 @@ impl StatsDisplay {
         // Hide cursor once at start
         print!("\x1B[?25l");
         let _ = io::stdout().flush();
-        Self { width, compact, full }
+        Self {
+            width,
+            compact,
+            full,
+            chart: None,
+            height: 0,
+            offset: 0,
+            interactive: false,
+            selected: None,
+            detail: false,
+            sort: Sort::default(),
+            group_by: None,
+            collapsed: HashSet::new(),
+            dim: Cell::new(false),
+            summary: false,
+            events: None,
+            cpu_mode: CpuMode::default(),
+            mem_scale: MemScale::default()
+        }
+    }
+
+    /// Lets the keyboard move a selection cursor over the containers.
+    pub fn interactive(mut self) -> Self {
+        self.interactive = true;
+        self
+    }
+
+    /// Switches to the full-screen chart view for a single container.
+    pub fn with_chart(mut self, container: &str) -> Self {
+        self.chart = Some(container.to_string());
+        self
+    }
+
+    /// Orders the containers by the given key and direction.
+    pub fn with_sort(mut self, sort: Sort) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    pub fn sort(&self) -> Sort { self.sort }
+
+    /// Picks what CPU percentages are relative to.
+    pub fn with_cpu_mode(mut self, cpu_mode: CpuMode) -> Self {
+        self.cpu_mode = cpu_mode;
+        self
+    }
+
+    /// Picks what the RAM bars are measured against.
+    pub fn with_mem_scale(mut self, mem_scale: MemScale) -> Self {
+        self.mem_scale = mem_scale;
+        self
+    }
+
+    /// Counts running, paused, exited and restarting containers above the list.
+    pub fn with_summary(mut self) -> Self {
+        self.summary = true;
+        self
+    }
+
+    /// Shows the latest events from the log in a panel under the list.
+    pub fn with_events(mut self, events: Arc<Mutex<EventLog>>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Splits the containers into sections.
+    pub fn with_group_by(mut self, group_by: GroupBy) -> Self {
+        self.group_by = Some(group_by);
+        self
+    }
+
+    /// Sorts by the next key, keeping the direction.
+    pub fn cycle_sort(&mut self) { self.sort.key = self.sort.key.next(); }
+
+    /// Flips the sort direction.
+    pub fn reverse_sort(&mut self) { self.sort.reverse = !self.sort.reverse; }
+
+    /// Lays out the next frames for a terminal of the given size, clearing the screen if it changed.
+    pub fn resize(&mut self, width: usize, height: usize) -> bool {
+        if (width, height) == (self.width, self.height) {
+            return false;
+        }
+
+        // Lines wrapped by the old size would otherwise be left behind
+        print!("\x1B[2J");
+        self.width = width;
+        self.height = height;
+        true
+    }
+
+    /// Moves on to the next page of containers, back to the first one after the last page.
+    pub fn next_page(&mut self, containers: &[ContainerState]) {
+        let entries = self.entries(containers);
+        let (compact, rows) = self.layout(&entries);
+        let visible = self.fit(&entries, self.offset, compact, rows);
+        self.offset = if self.offset + visible >= entries.len() { 0 } else { self.offset + visible };
+    }
+
+    /// Moves the selection cursor `delta` rows down, or up when negative.
+    pub fn select(&mut self, containers: &[ContainerState], delta: isize) {
+        let entries = self.entries(containers);
+        let index = match self.current(&entries) {
+            Some(index) => index
+                .saturating_add_signed(delta)
+                .min(entries.len().saturating_sub(1)),
+            None => 0
+        };
+        self.selected = entries.get(index).map(Entry::selection);
+    }
+
+    /// Opens the detail pane of the selected container, or folds the selected group.
+    pub fn open(&mut self) {
+        match &self.selected {
+            Some(Selection::Group(key)) if !self.collapsed.remove(key) => {
+                self.collapsed.insert(key.clone());
+            }
+            Some(Selection::Group(_)) => {}
+            Some(Selection::Container(_)) => self.detail = true,
+            None => {}
+        }
+    }
+
+    /// Goes back from the detail pane to the list.
+    pub fn close(&mut self) { self.detail = false; }
+
+    /// Lines up the list: every container, or each group's header followed by its containers unless it's collapsed.
+    fn entries<'a>(&self, containers: &'a [ContainerState]) -> Vec<Entry<'a>> {
+        let run = |states: &[&'a ContainerState]| {
+            states
+                .iter()
+                .enumerate()
+                .map(|(i, state)| Entry::Container {
+                    state,
+                    first: i == 0,
+                    last: i == states.len() - 1
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let Some(group_by) = &self.group_by else {
+            return run(&containers.iter().collect::<Vec<_>>());
+        };
+
+        let mut entries = Vec::new();
+        for group in group::group(group_by, containers) {
+            let containers = match self.collapsed.contains(&group.key) {
+                true => Vec::new(),
+                false => run(&group.containers)
+            };
+            entries.push(Entry::Header(group));
+            entries.extend(containers);
+        }
+        entries
+    }
+
+    /// Index of the selected entry, if it's still around.
+    fn current(&self, entries: &[Entry]) -> Option<usize> {
+        let selected = self.selected.as_ref()?;
+        entries.iter().position(|e| e.selection() == *selected)
+    }
+
+    /// How many lines an entry takes.
+    fn entry_height(&self, entry: &Entry, compact: bool) -> usize {
+        match entry {
+            _ if self.width < MIN_WIDTH => 1,
+            // The title and the CPU and RAM subtotal bars
+            Entry::Header(_) => 3,
+            Entry::Container { state, last, .. } => {
+                let stats = if self.full { 6 + breakdown_height(state) } else { 3 };
+                // Compact boxes share borders, only the last one of a run closes it
+                match compact {
+                    true => stats + usize::from(*last),
+                    false => stats + 1
+                }
+            }
+        }
+    }
+
+    /// Whether to use compact rows, and how many lines the entries get.
+    /// Full rows shrink to compact ones when they don't fit, then the entries are split into pages.
+    fn layout(&self, entries: &[Entry]) -> (bool, usize) {
+        // An unknown height fits everything
+        if self.height == 0 {
+            return (self.compact, usize::MAX);
+        }
+
+        // Leave room for the footer and the line the cursor rests on, so the frame never scrolls
+        let panels = usize::from(self.summary) + if self.events.is_some() { EVENT_ROWS + 1 } else { 0 };
+        let rows = self.height.saturating_sub(2 + panels);
+        let needed = |compact: bool| {
+            entries
+                .iter()
+                .map(|e| self.entry_height(e, compact))
+                .sum::<usize>()
+        };
+
+        if needed(self.compact) <= rows {
+            (self.compact, rows)
+        } else if needed(true) <= rows {
+            (true, rows)
+        } else {
+            // One more line goes to the "more below" indicator
+            (true, rows.saturating_sub(1))
+        }
+    }
+
+    /// How many entries from `offset` fit in `rows` lines, at least one.
+    fn fit(&self, entries: &[Entry], offset: usize, compact: bool, rows: usize) -> usize {
+        let (mut used, mut count) = (0, 0);
+
+        for entry in entries.iter().skip(offset) {
+            let height = self.entry_height(entry, compact);
+            // A run cut short by the page still needs its closing line
+            let closing = compact && self.width >= MIN_WIDTH && matches!(entry, Entry::Container { last: false, .. });
+
+            if count > 0 && used + height + usize::from(closing) > rows {
+                break;
+            }
+            used += height;
+            count += 1;
+        }
+
+        count
     }
 
     /// Print a line after erasing the current one to avoid leftover characters
     fn out_line(&self, line: &str) {
+        let line = if self.dim.get() { dim(line) } else { line.to_string() };
+
         // 2K – erase entire line, \r – carriage return, then newline
         print!("\x1B[2K\r{}\n", line);
     }
@@ -26,6 +382,28 @@ impl StatsDisplay {
     // Ensure cursor is shown again when the display is dropped (program exit)
 }
 
+// Views of the navigation state, which the binary only drives through the keyboard
+#[allow(dead_code)]
+impl StatsDisplay {
+    pub fn selected(&self) -> Option<&Selection> { self.selected.as_ref() }
+
+    /// Whether the detail pane is open.
+    pub fn detail(&self) -> bool { self.detail }
+
+    /// What the last frame's page showed, group headers and containers in order.
+    pub fn page(&self, containers: &[ContainerState]) -> Vec<Selection> {
+        let entries = self.entries(containers);
+        let (compact, rows) = self.layout(&entries);
+        let visible = self.fit(&entries, self.offset, compact, rows);
+        entries
+            .iter()
+            .skip(self.offset)
+            .take(visible)
+            .map(Entry::selection)
+            .collect()
+    }
+}
+
 impl Drop for StatsDisplay {
     fn drop(&mut self) {
         // Show cursor back
@@ -35,28 +413,210 @@ impl Drop for StatsDisplay {
 }
 
 impl StatsDisplay {
-    pub fn print_stats(&self, containers: &[DockerStats]) {
+    pub fn print_stats(&mut self, containers: &[ContainerState]) {
         // Move cursor to home (top-left) without erasing the entire screen
         print!("\x1B[H");
 
-        let mut max = 100f32;
+        if let Some(query) = &self.chart {
+            self.print_chart(containers.iter().find(|c| c.sample.matches(query)), query, self.height);
+            return self.end_frame();
+        }
+
+        let entries = self.entries(containers);
+
+        // Keep the cursor on something that is still around, leaving the detail pane of a container that's gone
+        let mut current = self.current(&entries);
+        if self.interactive && current.is_none() {
+            self.selected = entries.first().map(Entry::selection);
+            self.detail = false;
+            current = self.current(&entries);
+        }
+
+        let scale = Scale::of(containers, self.cpu_mode);
 
         if containers.is_empty() {
-            self.out_line("Waiting for container stats...");
+            self.out_line(&truncate("Waiting for container stats...", self.width));
+        } else if let Some(Entry::Container { state, .. }) = current.filter(|_| self.detail).map(|i| &entries[i]) {
+            self.print_detail(state, &scale);
         } else {
-            // Calculate global scale
-            for stats in containers {
-                let mem_perc = perc_to_float(&stats.mem_perc);
-                let cpu_perc = perc_to_float(&stats.cpu_perc);
-                max = max.max(mem_perc).max(cpu_perc);
+            let (compact, rows) = self.layout(&entries);
+            if self.summary {
+                self.print_summary(containers);
+            }
+
+            // Scroll just enough to bring the selection into view
+            self.offset = self.offset.min(entries.len().saturating_sub(1));
+            if let Some(index) = current {
+                self.offset = self.offset.min(index);
+                while self.offset + self.fit(&entries, self.offset, compact, rows) <= index {
+                    self.offset += 1;
+                }
             }
 
-            for (i, stats) in containers.iter().enumerate() {
-                self.print_container_stats(stats, i, containers.len(), max);
+            let visible = self.fit(&entries, self.offset, compact, rows);
+            let shown = &entries[self.offset..self.offset + visible];
+
+            // Subtotal bars are scaled against the busiest group
+            let busiest = entries
+                .iter()
+                .filter_map(|e| match e {
+                    Entry::Header(group) => Some(group.totals(self.cpu_mode)),
+                    Entry::Container { .. } => None
+                })
+                .fold(
+                    Totals {
+                        cpu_perc: 100.0,
+                        ..Totals::default()
+                    },
+                    Totals::max
+                );
+
+            for (i, entry) in shown.iter().enumerate() {
+                match entry {
+                    Entry::Header(group) => self.print_group_header(group, &busiest),
+                    Entry::Container { state, first, last } => self.print_container_stats(
+                        state,
+                        (*first || i == 0, *last || i == shown.len() - 1),
+                        &scale,
+                        (compact, self.full)
+                    )
+                }
             }
+
+            // Only count containers, headers aren't what's being paged through
+            let count = |entries: &[Entry]| entries.iter().filter(|e| e.is_container()).count();
+            let (above, below) = (count(&entries[..self.offset]), count(&entries[self.offset + visible..]));
+            if above > 0 || below > 0 {
+                let page = match (above, below) {
+                    (0, below) => format!("{below} more below"),
+                    (above, 0) => format!("{above} more above"),
+                    (above, below) => format!("{above} more above · {below} more below")
+                };
+                self.out_line(&truncate(&page, self.width).dimmed().to_string());
+            }
+
+            self.print_events();
+        }
+
+        self.end_frame();
+    }
+
+    /// The latest container events, newest first, under a title line.
+    fn print_events(&self) {
+        let Some(log) = &self.events else { return };
+        let Ok(log) = log.lock() else { return };
+
+        match self.width {
+            0..7 => self.out_line(&truncate("Events", self.width).bold().to_string()),
+            _ => self.out_line(&format!("{} {}", "Events".bold(), filler("─", self.width, 7).dimmed()))
+        }
+        if log.events().len() == 0 {
+            self.out_line(&truncate("No events yet", self.width).dimmed().to_string());
+        }
+
+        let now = unix_now();
+        for event in log.events().rev().take(EVENT_ROWS) {
+            let ago = format_duration(Duration::from_secs(now.saturating_sub(event.at)));
+            let line = truncate(
+                &format!("{ago:>7} ago  {} {}", event.name, event.kind),
+                self.width.saturating_sub(2)
+            );
+            let symbol = event.kind.symbol().to_string().color(event.kind.color());
+            match self.width {
+                0..2 => self.out_line(""),
+                _ => self.out_line(&format!("{symbol} {line}"))
+            }
+        }
+    }
+
+    /// A section header with the summed stats of the group, and bars of its CPU and memory against the busiest group.
+    fn print_group_header(&self, group: &Group, busiest: &Totals) {
+        let totals = group.totals(self.cpu_mode);
+        let rate = |v: f64| format!("{}/s", format_bytes(v.round() as u64, UnitType::Decimal));
+        let name = match (&group.key, &self.group_by) {
+            (Some(key), _) => key.clone(),
+            (None, Some(group_by)) => group_by.ungrouped(),
+            (None, None) => String::new()
+        };
+        let fold = match (self.interactive, self.collapsed.contains(&group.key)) {
+            (false, _) => "",
+            (true, false) => "▾ ",
+            (true, true) => "▸ "
+        };
+
+        let line = format!(
+            "{fold}{name} · {} · CPU {:.2}% · RAM {} · NET ↓ {} ↑ {}",
+            match group.containers.len() {
+                1 => "1 container".to_string(),
+                n => format!("{n} containers")
+            },
+            totals.cpu_perc,
+            format_bytes(totals.mem_usage, UnitType::Binary),
+            rate(totals.net_rx),
+            rate(totals.net_tx)
+        );
+        let line = truncate(&line, self.width).bold();
+
+        match self.selected == Some(Selection::Group(group.key.clone())) {
+            true => self.out_line(&line.reversed().to_string()),
+            false => self.out_line(&line.to_string())
         }
 
-        self.out_line("Press Ctrl+C to exit");
+        if self.width < MIN_WIDTH {
+            return;
+        }
+
+        // `  CPU |    12.50% ` in front of each bar
+        let bar_width = self.width - 18;
+        let bar = |value: f64, max: f64, text: String| {
+            let len = if max > 0.0 { ((value / max) * bar_width as f64) as usize } else { 0 };
+            let len = len.min(bar_width);
+            format!(
+                "{text:>9} {}{}",
+                usize_to_status(len, bar_width),
+                filler("░", bar_width, len).dimmed()
+            )
+        };
+
+        self.out_line(&format!(
+            "  CPU | {}",
+            bar(totals.cpu_perc, busiest.cpu_perc, format!("{:.2}%", totals.cpu_perc))
+        ));
+        self.out_line(&format!(
+            "  RAM | {}",
+            bar(
+                totals.mem_usage as f64,
+                busiest.mem_usage as f64,
+                format_bytes(totals.mem_usage, UnitType::Binary)
+            )
+        ));
+    }
+
+    /// Every stat of the selected container, with its history charted in the remaining rows.
+    fn print_detail(&self, state: &ContainerState, scale: &Scale) {
+        if self.width < MIN_WIDTH {
+            return self.print_narrow(state);
+        }
+
+        self.print_container_stats(state, (true, true), scale, (false, true));
+        self.print_chart(
+            Some(state),
+            &state.sample.name,
+            self.height.saturating_sub(7 + breakdown_height(state))
+        );
+    }
+
+    fn end_frame(&self) {
+        let help = match (self.interactive, self.detail) {
+            (false, _) => "Press Ctrl+C to exit".to_string(),
+            (true, false) => format!(
+                "↑/↓ select · enter details · s sort: {} {} · r reverse · q quit",
+                self.sort.key.name(),
+                if self.sort.descending() { "↓" } else { "↑" }
+            ),
+            (true, true) => "esc back · q quit".to_string()
+        };
+        self.out_line(&truncate(&help, self.width));
 
         // Clear anything below the current cursor position (in case the new frame is shorter)
         print!("\x1B[J");
@@ -65,98 +625,452 @@ impl StatsDisplay {
         let _ = io::stdout().flush();
     }
 
-    fn print_container_stats(&self, stats: &DockerStats, index: usize, total: usize, max: f32) {
+    /// Counts the containers by state, the ones that never started or can't be restarted count as exited.
+    fn print_summary(&self, containers: &[ContainerState]) {
+        let count = |states: &[&str]| {
+            containers
+                .iter()
+                .filter(|c| states.contains(&c.metadata.state.as_str()))
+                .count()
+        };
+
+        let line = format!(
+            "{} running · {} paused · {} exited · {} restarting",
+            count(&["running"]),
+            count(&["paused"]),
+            count(&["exited", "created", "dead"]),
+            count(&["restarting"])
+        );
+        self.out_line(&truncate(&line, self.width).bold().to_string());
+    }
+
+    /// A single unboxed line per container, for terminals too narrow to draw bars.
+    fn print_narrow(&self, state: &ContainerState) {
+        let stats = &state.sample;
+        let line = match (is_dimmed(state), badge(state)) {
+            (true, Some(badge)) => format!("{} {badge}", self.title(state)),
+            (stale, badge) => format!(
+                "{}{}{} CPU {:.1}% RAM {:.1}%",
+                if warnings(state).is_empty() { "" } else { "! " },
+                if badge.is_some() && !stale { "↻ " } else { "" },
+                self.title(state),
+                self.cpu_mode.perc(state),
+                stats.mem_perc
+            )
+        };
+        let line = truncate(&line, self.width);
+
+        self.dim.set(is_dimmed(state));
+        match self.is_selected(state) {
+            true => self.out_line(&line.reversed().to_string()),
+            false => self.out_line(&line)
+        }
+        self.dim.set(false);
+    }
+
+    /// Whether the selection cursor is on the given container.
+    fn is_selected(&self, state: &ContainerState) -> bool { self.selected == Some(Selection::Container(state.id().to_string())) }
+
+    /// The name a container's row goes by, which the grouping may replace.
+    fn title(&self, state: &ContainerState) -> String {
+        self.group_by
+            .as_ref()
+            .and_then(|g| g.title(state))
+            .unwrap_or_else(|| state.sample.name.clone())
+    }
+
+    fn print_container_stats(&self, state: &ContainerState, (first, last): (bool, bool), scale: &Scale, (compact, full): (bool, bool)) {
+        if self.width < MIN_WIDTH {
+            return self.print_narrow(state);
+        }
+
+        let (stats, max) = (&state.sample, scale.perc);
+        let spark = self.spark_width(state);
+        let spark_len = if spark > 0 { spark + 1 } else { 0 };
+        self.dim.set(is_dimmed(state));
+
         // LAYOUT
-        if !self.compact || index == 0 {
-            self.out_line(&format!("┌─ {} {}┐", stats.name, filler("─", self.width, stats.name.len() + 5)));
-        } else {
-            self.out_line(&format!("├─ {} {}┤", stats.name, fill_on_even("─", self.width, stats.name.len() + 5)));
+        let name = truncate(&self.title(state), self.width - 5);
+        let mut name_len = name.chars().count();
+        let mut name = match self.is_selected(state) {
+            true => name.reversed().to_string(),
+            false => name
+        };
+
+        for badge in warnings(state).into_iter().chain(badge(state)) {
+            if self.width >= name_len + 6 + badge.chars().count() {
+                name_len += badge.chars().count() + 1;
+                name = format!("{name} {badge}");
+            }
         }
 
-        let mem_perc = perc_to_float(&stats.mem_perc);
-        let cpu_perc = perc_to_float(&stats.cpu_perc);
+        // The full view fits what the runtime knows about the container next to its name
+        let room = self.width.saturating_sub(name_len + 8);
+        let details = details(&state.metadata);
+        if full && !details.is_empty() && room >= 4 {
+            let details = truncate(&details, room);
+            name_len += details.chars().count() + 3;
+            name = format!("{name} {} {}", "·".dimmed(), details.dimmed());
+        }
+
+        if !compact || first {
+            self.out_line(&format!("┌─ {name} {}┐", filler("─", self.width, name_len + 5)));
+        } else {
+            self.out_line(&format!("├─ {name} {}┤", fill_on_even("─", self.width, name_len + 5)));
+        }
 
-        // CPU
-        let scale_factor = (self.width - 18) as f32 / max;
-        let cpu_perc_scaled = (cpu_perc * scale_factor) as usize;
-        let cpu_padding = filler(" ", 7, stats.cpu_perc.len());
-        let cpu_status = usize_to_status(cpu_perc_scaled, self.width);
-        let cpu_fill = filler("░", self.width, cpu_perc_scaled + 18).dimmed();
+        // CPU, the history is kept in percent of a core
+        let (cpu, cpus) = (self.cpu_mode.perc(state), self.cpu_mode.cpus(state));
+        let cpu_perc = format!("{cpu:.2}%");
+        let cpu_spark = self.sparkline(&state.history.cpu, max as f64 * cpus, spark);
+        let scale_factor = self.width.saturating_sub(18 + spark_len) as f32 / max;
+        let cpu_perc_scaled = (cpu as f32 * scale_factor) as usize;
+        let cpu_padding = filler(" ", 7, cpu_perc.len());
+        // Colour the bar by how close it comes to 100%, not to the busiest container
+        let cpu_status = usize_to_status(cpu_perc_scaled, (100.0 * scale_factor) as usize);
+        let cpu_fill = filler("░", self.width, cpu_perc_scaled + 18 + spark_len).dimmed();
 
-        self.out_line(&format!("│ CPU | {cpu_padding}{} {cpu_status}{cpu_fill} │", stats.cpu_perc));
+        self.out_line(&format!("│ CPU | {cpu_padding}{cpu_perc} {cpu_spark}{cpu_status}{cpu_fill} │"));
 
         // RAM
-        let mem_usage_len = stats.mem_usage.len() + 1;
-        let scale_factor = (self.width - (18 + mem_usage_len)) as f32 / max;
-        let mem_perc_scaled = (mem_perc * scale_factor) as usize;
-        let mem_padding = filler(" ", 7, stats.mem_perc.len());
-        let mem_status = usize_to_status(mem_perc_scaled, self.width - (18 + mem_usage_len));
+        let mem_perc = format!("{:.2}%", stats.mem_perc);
+        let mem_usage = format!(
+            "{} / {}{}",
+            format_bytes(stats.mem_usage, UnitType::Binary),
+            format_bytes(stats.mem_limit, UnitType::Binary),
+            // Without a limit of its own, the container can use all of the host's memory
+            if state.mem_limited() == Some(false) { " host" } else { "" }
+        );
+        let (mem, mem_full, mem_max) = match self.mem_scale {
+            MemScale::Global => (
+                stats.mem_usage as f64,
+                stats.mem_limit as f64,
+                scale.mem.max(state.history.mem.max())
+            ),
+            MemScale::Local => (stats.mem_perc.min(100.0), 100.0, 100.0)
+        };
+        let spark_max = match self.mem_scale {
+            MemScale::Global => mem_max,
+            MemScale::Local => stats.mem_limit as f64
+        };
+        let mem_spark = self.sparkline(&state.history.mem, spark_max, spark);
+        // Drop the usage text rather than the bar when space runs out
+        let mem_usage = if self.width >= 18 + spark_len + mem_usage.len() + 1 + 8 { format!(" {mem_usage}") } else { String::new() };
+        let mem_usage_len = mem_usage.len() + spark_len;
+        let scale_factor = if mem_max > 0.0 { self.width.saturating_sub(18 + mem_usage_len) as f64 / mem_max } else { 0.0 };
+        let mem_perc_scaled = (mem * scale_factor) as usize;
+        let mem_padding = filler(" ", 7, mem_perc.len());
+        let mem_status = usize_to_status(mem_perc_scaled, (mem_full * scale_factor) as usize);
         let mem_fill = filler("░", self.width, mem_perc_scaled + (18 + mem_usage_len)).dimmed();
-        let mem_spacing = filler(" ", mem_usage_len, mem_usage_len);
 
         self.out_line(&format!(
-            "│ RAM | {mem_padding}{} {mem_status}{mem_fill}{mem_spacing} {} │",
-            stats.mem_perc, stats.mem_usage
+            "│ RAM | {mem_padding}{mem_perc} {mem_spark}{mem_status}{mem_fill}{mem_usage} │"
         ));
 
-        if self.full {
-            self.print_full_stats(stats);
+        if full {
+            self.print_full_stats(state, scale, spark);
         }
 
-        if !self.compact || index == total - 1 {
+        if !compact || last {
             self.out_line(&format!("└{}┘", filler("─", self.width, 2)));
         }
+        self.dim.set(false);
     }
 
-    fn print_full_stats(&self, stats: &DockerStats) {
+    fn print_full_stats(&self, state: &ContainerState, scale: &Scale, spark: usize) {
+        let (rates, history) = (&state.rates, &state.history);
         self.out_line(&format!("│{}│", fill_on_even("─", self.width, 2).dimmed()));
 
         // NET
-        if let Ok(net) = self.parse_network_stats(&stats.net_io) {
-            self.out_line(&format!(
-                "│ NET | {}{}{} │",
-                filler("▒", self.width - 11, net[0]).green(),
-                "░".dimmed(),
-                filler("▒", self.width - 11, net[1]).red()
-            ));
-        }
+        self.print_rates(
+            "NET",
+            [
+                ("↓", rates.net_rx, &history.net_rx, Color::Green),
+                ("↑", rates.net_tx, &history.net_tx, Color::Red)
+            ],
+            scale.net,
+            spark
+        );
 
         // IO
-        if let Ok(io) = self.parse_block_stats(&stats.block_io) {
-            self.out_line(&format!(
-                "│  IO | {}{}{} │",
-                filler("▒", io[0], 0).white(),
-                "░".dimmed(),
-                filler("▒", io[1], 0).black()
-            ));
+        self.print_rates(
+            "IO",
+            [
+                ("r", rates.block_read, &history.block_read, Color::White),
+                ("w", rates.block_write, &history.block_write, Color::Black)
+            ],
+            scale.io,
+            spark
+        );
+
+        if let Some(breakdown) = &state.sample.mem_breakdown {
+            self.print_breakdown(breakdown, state.sample.mem_limit);
         }
     }
 
-    fn parse_network_stats(&self, net_io: &str) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
-        let parts: Vec<&str> = net_io.split(" / ").collect();
-        if parts.len() != 2 {
-            return Ok(balanced_split(self.width - 11));
+    /// Prints where the memory goes as a bar stacked against the limit, with a legend under it.
+    /// The parts the source can't measure are left out of both.
+    fn print_breakdown(&self, breakdown: &MemoryBreakdown, limit: u64) {
+        let segments: Vec<(&str, u64, Color)> = [
+            ("anon", Some(breakdown.anon), Color::Magenta),
+            ("file", Some(breakdown.file), Color::Cyan),
+            ("kernel", breakdown.kernel, Color::Yellow),
+            ("swap", breakdown.swap, Color::Red)
+        ]
+        .into_iter()
+        .filter_map(|(name, bytes, color)| Some((name, bytes?, color)))
+        .collect();
+        let bar_width = self.width.saturating_sub(10);
+        let max = limit.max(breakdown.total()) as f64;
+
+        // Round where each segment ends rather than their lengths, so they add up to the bar
+        let (mut bar, mut end, mut sum) = (String::new(), 0, 0);
+        for &(_, bytes, color) in &segments {
+            sum += bytes;
+            let next = if max > 0.0 { (sum as f64 / max * bar_width as f64).round() as usize } else { 0 };
+            bar.push_str(
+                &filler("█", next.min(bar_width), end)
+                    .color(color)
+                    .to_string()
+            );
+            end = end.max(next.min(bar_width));
+        }
+        self.out_line(&format!("│ MEM | {bar}{} │", filler("░", bar_width, end).dimmed()));
+
+        // Keep the legend to the entries that fit
+        let (mut legend, mut len) = (String::new(), 0);
+        for (name, bytes, color) in segments {
+            let entry = format!("{name} {}", format_bytes(bytes, UnitType::Binary));
+            let entry_len = entry.chars().count() + if len > 0 { 4 } else { 2 };
+            if len + entry_len > bar_width {
+                break;
+            }
+            let gap = if len > 0 { "  " } else { "" };
+            legend.push_str(&format!("{gap}{} {entry}", "■".color(color)));
+            len += entry_len;
+        }
+        self.out_line(&format!("│       {legend}{} │", filler(" ", bar_width, len)));
+    }
+
+    /// Prints a pair of per-second rates, each with its own sparkline and a bar scaled against `max`.
+    fn print_rates(&self, label: &str, rates: [(&str, f64, &History, Color); 2], max: f64, spark: usize) {
+        // Each half is `x 999.99MB/s ` followed by its sparkline and bar
+        let spark = if self.width < 37 + 2 * (spark + 1) + 2 { 0 } else { spark };
+        let spark_len = if spark > 0 { spark + 1 } else { 0 };
+        if self.width < 37 {
+            let [(a, ra, ..), (b, rb, ..)] = rates;
+            let (ra, rb) = (
+                format_bytes(ra.round() as u64, UnitType::Decimal),
+                format_bytes(rb.round() as u64, UnitType::Decimal)
+            );
+            let text = truncate(&format!("{label:>3} | {a} {ra}/s {b} {rb}/s"), self.width - 4);
+            return self.out_line(&format!("│ {text}{} │", filler(" ", self.width - 4, text.chars().count())));
+        }
+
+        let available = self.width.saturating_sub(37 + 2 * spark_len);
+        let bar_width = available / 2;
+        let spark_max = rates
+            .iter()
+            .fold(max, |m, (_, _, history, _)| m.max(history.max()));
+
+        let halves: Vec<String> = rates
+            .iter()
+            .map(|(arrow, rate, history, color)| {
+                let len = if max > 0.0 { (rate / max * bar_width as f64).round() as usize } else { 0 };
+                let len = len.min(bar_width);
+                let text = format!("{}/s", format_bytes(rate.round() as u64, UnitType::Decimal));
+
+                format!(
+                    "{arrow} {text:>10} {}{}{}",
+                    self.sparkline(history, spark_max, spark),
+                    filler("▒", len, 0).color(*color),
+                    filler("░", bar_width, len).dimmed()
+                )
+            })
+            .collect();
+
+        let padding = filler(" ", available % 2, 0);
+        self.out_line(&format!("│ {label:>3} | {} {}{padding} │", halves[0], halves[1]));
+    }
+
+    /// How many samples of history fit next to the bars, none on narrow terminals where the bars need the room.
+    fn spark_width(&self, state: &ContainerState) -> usize {
+        match self.width {
+            ..40 => 0,
+            width => (state.history.cpu.window().as_secs() as usize).min(width / 8)
         }
+    }
 
-        let bytes = vec![
-            Byte::parse_str(parts[0], true)?.as_u128(),
-            Byte::parse_str(parts[1], true)?.as_u128(),
-        ];
+    /// A dimmed sparkline followed by a separating space, or nothing when history is disabled.
+    fn sparkline(&self, history: &History, max: f64, width: usize) -> String {
+        if width == 0 {
+            return String::new();
+        }
 
-        Ok(scale_between(bytes, 1, self.width - 12).unwrap_or_else(|| balanced_split(self.width - 11)))
+        format!("{} ", sparkline(history.values(), max, width).cyan())
     }
 
-    fn parse_block_stats(&self, block_io: &str) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
-        let parts: Vec<&str> = block_io.split(" / ").collect();
-        if parts.len() != 2 {
-            return Ok(balanced_split(self.width - 11));
+    /// Prints CPU, memory, network and disk line charts for one container, filling `height` rows.
+    fn print_chart(&self, state: Option<&ContainerState>, query: &str, height: usize) {
+        let Some(state) = state else {
+            self.out_line(&truncate(&format!("Waiting for stats of {query}..."), self.width));
+            return;
+        };
+        if self.width < MIN_WIDTH {
+            return self.print_narrow(state);
         }
 
-        let bytes = vec![
-            Byte::parse_str(parts[0], true)?.as_u128(),
-            Byte::parse_str(parts[1], true)?.as_u128(),
-        ];
+        let (stats, rates, history) = (&state.sample, &state.rates, &state.history);
+        let cpus = self.cpu_mode.cpus(state);
+        let percent = |v: f64| format!("{:.1}%", v / cpus);
+        let bytes = |v: f64| format_bytes(v.round() as u64, UnitType::Binary);
+        let rate = |v: f64| format!("{}/s", format_bytes(v.round() as u64, UnitType::Decimal));
+
+        // Four panels, each with a title above its plot and a time axis and event marks below, and the footer under them
+        let rows = (height.saturating_sub(2) / 4).saturating_sub(3).max(1);
+        let name = truncate(&stats.name, self.width / 2);
+        let panel = |title: String, series: &[Series], max: f64, label: &dyn Fn(f64) -> String| {
+            let title = truncate(&title, self.width - name.chars().count() - 3);
+            self.out_line(&format!("{} · {title}", name.bold()));
+            chart::line_chart(series, &history.events, max, self.width, rows, label)
+                .iter()
+                .for_each(|line| self.out_line(line));
+        };
+
+        panel(
+            format!("CPU {:.2}%", self.cpu_mode.perc(state)),
+            &[Series::new(&history.cpu, Color::Cyan)],
+            history.cpu.max().max(1.0),
+            &percent
+        );
+
+        panel(
+            format!("Memory {} / {}", bytes(stats.mem_usage as f64), bytes(stats.mem_limit as f64)),
+            &[Series::new(&history.mem, Color::Magenta)],
+            history.mem.max().max(1024.0 * 1024.0),
+            &bytes
+        );
+
+        panel(
+            format!("Network ↓ {} ↑ {}", rate(rates.net_rx), rate(rates.net_tx)),
+            &[Series::new(&history.net_rx, Color::Green), Series::new(&history.net_tx, Color::Red)],
+            history.net_rx.max().max(history.net_tx.max()).max(1000.0),
+            &rate
+        );
 
-        Ok(scale_between(bytes, 1, self.width - 12).unwrap_or_else(|| balanced_split(self.width - 11)))
+        panel(
+            format!("Disk r {} w {}", rate(rates.block_read), rate(rates.block_write)),
+            &[
+                Series::new(&history.block_read, Color::White),
+                Series::new(&history.block_write, Color::BrightBlack)
+            ],
+            history
+                .block_read
+                .max()
+                .max(history.block_write.max())
+                .max(1000.0),
+            &rate
+        );
     }
 }
+
+/// The lines the memory breakdown adds to a full box, if the source breaks the memory down.
+fn breakdown_height(state: &ContainerState) -> usize {
+    if state.sample.mem_breakdown.is_some() {
+        2
+    } else {
+        0
+    }
+}
+
+/// Warns about a container that got OOM-killed or keeps restarting, with how often it happened this session.
+fn warnings(state: &ContainerState) -> Vec<ColoredString> {
+    let incidents = &state.incidents;
+    let mut warnings = Vec::new();
+
+    if incidents.ooms() > 0 {
+        warnings.push(format!("OOM-killed ×{}", incidents.ooms()).red().bold());
+    }
+    if state.is_crash_looping() {
+        // The runtime may report it before any restart was seen
+        let warning = match incidents.restarts() {
+            0 => "crash loop".to_string(),
+            restarts => format!("crash loop ×{restarts}")
+        };
+        warnings.push(warning.red().bold());
+    }
+
+    warnings
+}
+
+/// Flags a container that's gone, or recreated lately so a row starting over from an empty history doesn't go unnoticed.
+fn badge(state: &ContainerState) -> Option<ColoredString> {
+    let ago = |at: Instant| format_duration(at.elapsed());
+
+    if state.is_stopped() {
+        let metadata = &state.metadata;
+        let badge = match (metadata.state.as_str(), metadata.finished_at) {
+            ("exited", Some(at)) => {
+                let ago = format_duration(Duration::from_secs(unix_now().saturating_sub(at)));
+                format!("exited ({}) {ago} ago", metadata.exit_code)
+            }
+            ("exited", None) => format!("exited ({})", metadata.exit_code),
+            (other, _) => other.to_string()
+        };
+        return Some(badge.normal());
+    }
+    if let Some(exited) = state.exited {
+        return Some(format!("exited {} ago", ago(exited)).normal());
+    }
+    if state.is_stale() {
+        return Some(format!("last seen {} ago", ago(state.last_seen())).normal());
+    }
+
+    let recreated = state.recreated.filter(|at| at.elapsed() < RECREATED_FOR)?;
+    Some(format!("↻ recreated {} ago", ago(recreated)).yellow())
+}
+
+/// Whether a container is drawn greyed out, because it's gone quiet or isn't running.
+fn is_dimmed(state: &ContainerState) -> bool { state.is_stale() || state.is_stopped() }
+
+/// Greys out a line, dropping its colours but keeping the selection's reverse video and the warnings.
+fn dim(line: &str) -> String {
+    static STYLES: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\x1B\[[0-9;]*m").unwrap());
+
+    // Every reset would end the dimming too, so dim again right after
+    let line = STYLES.replace_all(line, |caps: &regex::Captures| match &caps[0] {
+        "\x1B[7m" => "\x1B[7m",
+        "\x1B[1;31m" => "\x1B[1;31m",
+        "\x1B[0m" => "\x1B[0;2m",
+        _ => ""
+    });
+    line.dimmed().to_string()
+}
+
+/// Summarizes a container's metadata, e.g. `nginx:1.27 · running 2h 13m · healthy · 1 restart · 8080→80/tcp`.
+fn details(metadata: &Metadata) -> String {
+    let mut parts = Vec::new();
+
+    if !metadata.image.is_empty() {
+        parts.push(metadata.image.clone());
+    }
+    if !metadata.state.is_empty() {
+        match metadata.uptime(unix_now()) {
+            Some(uptime) if metadata.state == "running" => parts.push(format!("running {}", format_duration(uptime))),
+            _ => parts.push(metadata.state.clone())
+        }
+    }
+    if let Some(health) = &metadata.health {
+        parts.push(health.clone());
+    }
+    if metadata.started_at.is_some() {
+        let plural = if metadata.restarts == 1 { "" } else { "s" };
+        parts.push(format!("{} restart{plural}", metadata.restarts));
+    }
+    if !metadata.ports.is_empty() {
+        let ports: Vec<String> = metadata.ports.iter().map(Port::to_string).collect();
+        parts.push(ports.join(", "));
+    }
+
+    parts.join(" · ")
+}
@@ -0,0 +1,582 @@
+use crate::{
+    data::{ContainerSample, MemoryBreakdown, Metadata, Port},
+    error::{AppError, Result},
+    event::{Event, EventKind},
+    utils::parse_timestamp
+};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Read, Write},
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf}
+};
+
+/// Default location of the Docker daemon socket.
+pub const DEFAULT_SOCKET: &str = "/var/run/docker.sock";
+
+/// Location of the Podman socket when running as root.
+pub const PODMAN_ROOTFUL_SOCKET: &str = "/run/podman/podman.sock";
+
+/// A minimal Docker Engine API client speaking HTTP/1.1 over the daemon's Unix socket.
+#[derive(Debug, Clone)]
+pub struct EngineClient {
+    socket: PathBuf
+}
+
+impl EngineClient {
+    pub fn new(socket: impl Into<PathBuf>) -> Self { Self { socket: socket.into() } }
+
+    /// Builds a client from `DOCKER_HOST` when it points to a Unix socket, falling back to the default socket.
+    pub fn from_env() -> Self {
+        match std::env::var("DOCKER_HOST") {
+            Ok(host) if host.starts_with("unix://") => Self::new(host.trim_start_matches("unix://")),
+            _ => Self::new(DEFAULT_SOCKET)
+        }
+    }
+
+    /// Builds a client for Podman's Docker-compatible socket, rootless first, then rootful.
+    pub fn podman() -> Self {
+        let rootless = std::env::var_os("XDG_RUNTIME_DIR").map(|dir| PathBuf::from(dir).join("podman/podman.sock"));
+
+        match rootless {
+            Some(path) if path.exists() => Self::new(path),
+            _ => Self::new(PODMAN_ROOTFUL_SOCKET)
+        }
+    }
+
+    pub fn socket(&self) -> &Path { &self.socket }
+
+    /// Lists the running containers, or every container with `all` (`GET /containers/json`).
+    pub fn list_containers(&self, all: bool) -> Result<Vec<ContainerSummary>> {
+        let body = self.get(if all { "/containers/json?all=true" } else { "/containers/json" })?;
+        Ok(serde_json::from_reader(body)?)
+    }
+
+    /// Fetches the details the listing leaves out (`GET /containers/{id}/json`).
+    pub fn inspect_container(&self, id: &str) -> Result<ContainerDetails> {
+        let body = self.get(&format!("/containers/{id}/json"))?;
+        Ok(serde_json::from_reader(body)?)
+    }
+
+    /// Opens a live stats stream for a container (`GET /containers/{id}/stats?stream=true`).
+    pub fn stream_stats(&self, id: &str) -> Result<StatsStream> {
+        let body = self.get(&format!("/containers/{id}/stats?stream=true"))?;
+        Ok(StatsStream {
+            inner: serde_json::Deserializer::from_reader(body).into_iter()
+        })
+    }
+
+    /// Opens a live stream of container events (`GET /events`).
+    pub fn stream_events(&self) -> Result<EventStream> {
+        // `filters={"type":["container"]}`, URL-encoded
+        let body = self.get("/events?filters=%7B%22type%22%3A%5B%22container%22%5D%7D")?;
+        Ok(EventStream {
+            inner: serde_json::Deserializer::from_reader(body).into_iter()
+        })
+    }
+
+    /// Sends a GET request and returns the response body, failing on non-2xx statuses.
+    fn get(&self, path: &str) -> Result<Box<dyn Read + Send>> {
+        let mut stream = UnixStream::connect(&self.socket).map_err(|e| AppError::SocketUnavailable(self.socket.display().to_string(), e))?;
+
+        write!(
+            stream,
+            "GET {path} HTTP/1.1\r\nHost: docker\r\nUser-Agent: ds/{}\r\nConnection: close\r\n\r\n",
+            env!("CARGO_PKG_VERSION")
+        )?;
+        stream.flush()?;
+
+        let mut reader = BufReader::new(stream);
+        let (status, headers) = read_head(&mut reader)?;
+
+        let mut body: Box<dyn Read + Send> = if headers
+            .get("transfer-encoding")
+            .is_some_and(|v| v.eq_ignore_ascii_case("chunked"))
+        {
+            Box::new(ChunkedReader::new(reader))
+        } else if let Some(len) = headers
+            .get("content-length")
+            .and_then(|v| v.parse::<u64>().ok())
+        {
+            Box::new(reader.take(len))
+        } else {
+            Box::new(reader)
+        };
+
+        if !(200..300).contains(&status) {
+            let mut text = String::new();
+            body.read_to_string(&mut text)?;
+
+            let message = serde_json::from_str::<ErrorResponse>(&text)
+                .map(|e| e.message)
+                .unwrap_or_else(|_| text.trim().to_string());
+
+            return Err(AppError::ApiError(status, message));
+        }
+
+        Ok(body)
+    }
+}
+
+/// Reads the status line and headers of an HTTP response.
+fn read_head(reader: &mut impl BufRead) -> Result<(u16, HashMap<String, String>)> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+
+    let status = line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse::<u16>().ok())
+        .ok_or_else(|| AppError::ApiError(0, format!("Malformed status line: {}", line.trim())))?;
+
+    let mut headers = HashMap::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+
+        if let Some((key, value)) = line.split_once(':') {
+            headers.insert(key.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    Ok((status, headers))
+}
+
+/// Decodes an HTTP/1.1 `Transfer-Encoding: chunked` body.
+struct ChunkedReader<R> {
+    inner: R,
+    remaining: usize,
+    done: bool
+}
+
+impl<R: BufRead> ChunkedReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            remaining: 0,
+            done: false
+        }
+    }
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.remaining == 0 {
+            let mut line = String::new();
+            if self.inner.read_line(&mut line)? == 0 {
+                self.done = true;
+                return Ok(0);
+            }
+
+            let size = line.trim().split(';').next().unwrap_or_default();
+            self.remaining = usize::from_str_radix(size, 16).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+            if self.remaining == 0 {
+                self.done = true;
+                return Ok(0);
+            }
+        }
+
+        let max = buf.len().min(self.remaining);
+        let read = self.inner.read(&mut buf[..max])?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        self.remaining -= read;
+        if self.remaining == 0 {
+            // Every chunk is terminated by a CRLF
+            self.inner.read_line(&mut String::new())?;
+        }
+
+        Ok(read)
+    }
+}
+
+/// An endless iterator over the stats objects sent by the daemon.
+pub struct StatsStream {
+    inner: serde_json::StreamDeserializer<'static, serde_json::de::IoRead<Box<dyn Read + Send>>, StatsResponse>
+}
+
+impl Iterator for StatsStream {
+    type Item = Result<StatsResponse>;
+
+    fn next(&mut self) -> Option<Self::Item> { self.inner.next().map(|r| r.map_err(AppError::from)) }
+}
+
+/// An endless stream of events, one JSON object at a time.
+pub struct EventStream {
+    inner: serde_json::StreamDeserializer<'static, serde_json::de::IoRead<Box<dyn Read + Send>>, EventMessage>
+}
+
+impl Iterator for EventStream {
+    type Item = Result<EventMessage>;
+
+    fn next(&mut self) -> Option<Self::Item> { self.inner.next().map(|r| r.map_err(AppError::from)) }
+}
+
+/// A single object of `GET /events`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct EventMessage {
+    #[serde(rename = "Type")]
+    pub kind: String,
+    #[serde(rename = "Action")]
+    pub action: String,
+    #[serde(rename = "Actor")]
+    pub actor: EventActor,
+    /// Seconds since the Unix epoch.
+    pub time: u64
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct EventActor {
+    #[serde(rename = "ID")]
+    pub id: String,
+    /// The container's name, image and labels, along with details of the event such as `exitCode`.
+    pub attributes: HashMap<String, String>
+}
+
+impl EventMessage {
+    /// The container event worth showing, if this is one.
+    pub fn event(&self) -> Option<Event> {
+        if self.kind != "container" {
+            return None;
+        }
+
+        let attributes = &self.actor.attributes;
+        Some(Event {
+            at: self.time,
+            id: self.actor.id.clone(),
+            name: attributes.get("name").cloned().unwrap_or_default(),
+            kind: EventKind::parse(&self.action, attributes)?
+        })
+    }
+
+    /// The container as far as the filter is concerned, labels being among the attributes.
+    pub fn summary(&self) -> ContainerSummary {
+        let attributes = &self.actor.attributes;
+        ContainerSummary {
+            id: self.actor.id.clone(),
+            names: attributes.get("name").into_iter().cloned().collect(),
+            image: attributes.get("image").cloned().unwrap_or_default(),
+            labels: Some(attributes.clone()),
+            ..ContainerSummary::default()
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorResponse {
+    message: String
+}
+
+/// An entry of `GET /containers/json`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct ContainerSummary {
+    pub id: String,
+    pub names: Vec<String>,
+    pub image: String,
+    #[serde(rename = "ImageID")]
+    pub image_id: String,
+    /// Podman may report `null` rather than an empty map.
+    pub labels: Option<HashMap<String, String>>,
+    pub state: String,
+    pub status: String,
+    /// Podman may report `null` when nothing is published.
+    pub ports: Option<Vec<PortSummary>>
+}
+
+/// A port mapping of `GET /containers/json`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct PortSummary {
+    #[serde(rename = "IP")]
+    pub ip: Option<String>,
+    pub private_port: u16,
+    /// Missing for exposed ports that aren't published.
+    pub public_port: Option<u16>,
+    #[serde(rename = "Type")]
+    pub protocol: String
+}
+
+/// The parts of `GET /containers/{id}/json` missing from the listing.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct ContainerDetails {
+    pub restart_count: u64,
+    pub state: ContainerDetailsState,
+    pub host_config: HostConfig
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct ContainerDetailsState {
+    pub status: String,
+    #[serde(rename = "OOMKilled")]
+    pub oom_killed: bool,
+    pub exit_code: i64,
+    /// An RFC 3339 timestamp.
+    pub started_at: String,
+    /// An RFC 3339 timestamp, Docker's zero time while the container never stopped.
+    pub finished_at: String,
+    /// Older Podman versions call it `Healthcheck`.
+    #[serde(alias = "Healthcheck")]
+    pub health: Option<Health>
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct Health {
+    pub status: String
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct HostConfig {
+    /// The memory limit in bytes, 0 without one.
+    pub memory: u64,
+    /// The CPU limit set with `--cpus`, in billionths of a CPU.
+    pub nano_cpus: u64,
+    /// The CPU limit set with `--cpu-quota`, in microseconds per `cpu_period`.
+    pub cpu_quota: i64,
+    pub cpu_period: u64
+}
+
+impl HostConfig {
+    /// The CPU limit in CPUs, `None` without one.
+    pub fn cpu_limit(&self) -> Option<f64> {
+        if self.nano_cpus > 0 {
+            return Some(self.nano_cpus as f64 / 1e9);
+        }
+
+        // The period defaults to 100ms
+        let period = if self.cpu_period > 0 { self.cpu_period } else { 100_000 };
+        (self.cpu_quota > 0).then(|| self.cpu_quota as f64 / period as f64)
+    }
+}
+
+impl ContainerDetails {
+    /// Adds the details to metadata built from the listing.
+    pub fn fill(&self, metadata: &mut Metadata) {
+        metadata.started_at = parse_timestamp(&self.state.started_at);
+        metadata.finished_at = parse_timestamp(&self.state.finished_at);
+        metadata.exit_code = self.state.exit_code;
+        metadata.oom_killed = self.state.oom_killed;
+        metadata.health = self
+            .state
+            .health
+            .as_ref()
+            .map(|h| h.status.clone())
+            .filter(|s| !s.is_empty());
+        metadata.restarts = self.restart_count;
+        metadata.mem_limited = Some(self.host_config.memory > 0);
+        metadata.cpu_limit = self.host_config.cpu_limit();
+    }
+}
+
+impl ContainerSummary {
+    /// The primary container name without Docker's leading slash.
+    pub fn name(&self) -> &str {
+        self.names
+            .first()
+            .map(|n| n.trim_start_matches('/'))
+            .unwrap_or_default()
+    }
+
+    /// Whether the container is referenced by `query`, either by name or by (a prefix of) its ID.
+    pub fn matches(&self, query: &str) -> bool { self.name() == query || (!query.is_empty() && self.id.starts_with(query)) }
+
+    /// Whether the container isn't running, nor paused or restarting.
+    pub fn is_stopped(&self) -> bool { is_stopped(&self.state) }
+}
+
+/// Whether a container state is one without a process, so without stats either.
+pub fn is_stopped(state: &str) -> bool { matches!(state, "exited" | "created" | "dead") }
+
+/// A single object of `GET /containers/{id}/stats`.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct StatsResponse {
+    pub id: String,
+    pub name: String,
+    pub cpu_stats: CpuStats,
+    pub precpu_stats: CpuStats,
+    pub memory_stats: MemoryStats,
+    pub networks: Option<HashMap<String, NetworkStats>>,
+    pub blkio_stats: BlkioStats
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct CpuStats {
+    pub cpu_usage: CpuUsage,
+    pub system_cpu_usage: Option<u64>,
+    pub online_cpus: Option<u32>
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct CpuUsage {
+    pub total_usage: u64,
+    pub percpu_usage: Option<Vec<u64>>
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct MemoryStats {
+    pub usage: Option<u64>,
+    pub limit: Option<u64>,
+    pub stats: Option<HashMap<String, u64>>
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct NetworkStats {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct BlkioStats {
+    pub io_service_bytes_recursive: Option<Vec<BlkioEntry>>
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct BlkioEntry {
+    pub op: String,
+    pub value: u64
+}
+
+impl StatsResponse {
+    /// CPU usage as a percentage of a single core, computed the same way `docker stats` does.
+    pub fn cpu_percent(&self) -> f64 {
+        let cpu_delta = self
+            .cpu_stats
+            .cpu_usage
+            .total_usage
+            .saturating_sub(self.precpu_stats.cpu_usage.total_usage);
+        let system_delta = self
+            .cpu_stats
+            .system_cpu_usage
+            .unwrap_or_default()
+            .saturating_sub(self.precpu_stats.system_cpu_usage.unwrap_or_default());
+
+        let cpus = self.online_cpus().unwrap_or(1);
+
+        if cpu_delta == 0 || system_delta == 0 {
+            0.0
+        } else {
+            cpu_delta as f64 / system_delta as f64 * cpus as f64 * 100.0
+        }
+    }
+
+    /// How many CPUs the host has, older daemons only list their usage one by one.
+    pub fn online_cpus(&self) -> Option<u32> {
+        self.cpu_stats.online_cpus.or_else(|| {
+            self.cpu_stats
+                .cpu_usage
+                .percpu_usage
+                .as_ref()
+                .map(|p| p.len() as u32)
+        })
+    }
+
+    /// Memory in use, excluding the page cache like the docker CLI does.
+    pub fn memory_usage(&self) -> u64 {
+        let usage = self.memory_stats.usage.unwrap_or_default();
+        let cache = self.memory_stats.stats.as_ref().and_then(|s| {
+            // cgroup v1 reports `total_inactive_file`, v2 reports `inactive_file`
+            s.get("total_inactive_file")
+                .or_else(|| s.get("inactive_file"))
+                .copied()
+        });
+
+        usage.saturating_sub(cache.unwrap_or_default())
+    }
+
+    pub fn memory_limit(&self) -> u64 { self.memory_stats.limit.unwrap_or_default() }
+
+    /// Total received and transmitted bytes over all interfaces.
+    pub fn network_io(&self) -> (u64, u64) {
+        self.networks
+            .iter()
+            .flatten()
+            .fold((0, 0), |(rx, tx), (_, net)| (rx + net.rx_bytes, tx + net.tx_bytes))
+    }
+
+    /// Total bytes read from and written to block devices.
+    pub fn block_io(&self) -> (u64, u64) {
+        self.blkio_stats
+            .io_service_bytes_recursive
+            .iter()
+            .flatten()
+            .fold((0, 0), |(read, write), entry| match entry.op.to_ascii_lowercase().as_str() {
+                "read" => (read + entry.value, write),
+                "write" => (read, write + entry.value),
+                _ => (read, write)
+            })
+    }
+}
+
+impl From<&ContainerSummary> for Metadata {
+    fn from(summary: &ContainerSummary) -> Self {
+        let mut ports: Vec<Port> = Vec::new();
+        for port in summary.ports.iter().flatten() {
+            let Some(public) = port.public_port else { continue };
+            let port = Port {
+                ip: port
+                    .ip
+                    .clone()
+                    .filter(|ip| !matches!(ip.as_str(), "" | "0.0.0.0" | "::")),
+                public,
+                private: port.private_port,
+                protocol: port.protocol.clone()
+            };
+
+            // Docker lists a port bound to every interface once for IPv4 and once for IPv6
+            if !ports.contains(&port) {
+                ports.push(port);
+            }
+        }
+
+        Self {
+            image: summary.image.clone(),
+            labels: summary.labels.clone().unwrap_or_default(),
+            state: summary.state.clone(),
+            ports,
+            ..Self::default()
+        }
+    }
+}
+
+impl From<StatsResponse> for ContainerSample {
+    fn from(stats: StatsResponse) -> Self {
+        let mut sample = ContainerSample::new(
+            &stats.id,
+            stats.name.trim_start_matches('/'),
+            stats.cpu_percent(),
+            (stats.memory_usage(), stats.memory_limit()),
+            stats.network_io(),
+            stats.block_io()
+        );
+        sample.host_cpus = stats.online_cpus();
+        sample.mem_breakdown = stats
+            .memory_stats
+            .stats
+            .as_ref()
+            .and_then(MemoryBreakdown::from_stat);
+        sample
+    }
+}
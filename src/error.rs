@@ -4,10 +4,15 @@ use std::fmt;
 pub enum AppError {
     DockerNotFound,
     DockerNotRunning,
-    #[allow(dead_code)]
+    ApiError(u16, String),
+    /// The runtime's API socket at the path couldn't be connected to.
+    SocketUnavailable(String, std::io::Error),
+    CommandFailed(String),
     JsonParseError(String),
     IoError(std::io::Error),
-    TerminalError(String)
+    TerminalError(String),
+    /// Flags that can't be used together, or with the chosen source.
+    Usage(String)
 }
 
 impl fmt::Display for AppError {
@@ -15,9 +20,15 @@ impl fmt::Display for AppError {
         match self {
             AppError::DockerNotFound => write!(f, "Docker command not found. Please install Docker."),
             AppError::DockerNotRunning => write!(f, "Docker daemon is not running. Please start Docker."),
+            AppError::ApiError(status, msg) => write!(f, "Docker API error ({status}): {msg}"),
+            AppError::SocketUnavailable(path, err) => {
+                write!(f, "Can't connect to the container runtime at {path}: {err}. Is the daemon running?")
+            }
+            AppError::CommandFailed(msg) => write!(f, "Command failed: {msg}"),
             AppError::JsonParseError(msg) => write!(f, "Failed to parse Docker stats: {msg}"),
             AppError::IoError(err) => write!(f, "IO error: {err}"),
-            AppError::TerminalError(msg) => write!(f, "Terminal error: {msg}")
+            AppError::TerminalError(msg) => write!(f, "Terminal error: {msg}"),
+            AppError::Usage(msg) => write!(f, "{msg}")
         }
     }
 }
@@ -34,5 +45,14 @@ impl From<std::io::Error> for AppError {
     }
 }
 
+impl From<serde_json::Error> for AppError {
+    fn from(err: serde_json::Error) -> Self {
+        match err.classify() {
+            serde_json::error::Category::Io => AppError::from(std::io::Error::from(err)),
+            _ => AppError::JsonParseError(err.to_string())
+        }
+    }
+}
+
 #[allow(dead_code)]
 pub type Result<T> = std::result::Result<T, AppError>;
@@ -0,0 +1,126 @@
+use colored::Color;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt
+};
+
+/// How many events the panel keeps around.
+pub const EVENT_LOG_CAPACITY: usize = 100;
+
+/// A container lifecycle event worth showing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventKind {
+    Start,
+    Stop,
+    /// The process exited, with its exit code.
+    Die(i64),
+    Oom,
+    Restart,
+    /// The health check status changed.
+    Health(String)
+}
+
+impl EventKind {
+    /// Reads the kind from an event's action and attributes, `None` for the ones not worth showing.
+    pub fn parse(action: &str, attributes: &HashMap<String, String>) -> Option<Self> {
+        // Docker puts the new status in the action, as in `health_status: healthy`
+        if let Some(health) = action.strip_prefix("health_status") {
+            let status = match health.trim_start_matches(':').trim() {
+                "" => attributes.get("health_status")?.clone(),
+                status => status.to_string()
+            };
+            return Some(Self::Health(status));
+        }
+
+        match action {
+            "start" => Some(Self::Start),
+            "stop" => Some(Self::Stop),
+            "die" => Some(Self::Die(
+                attributes
+                    .get("exitCode")
+                    .and_then(|c| c.parse().ok())
+                    .unwrap_or_default()
+            )),
+            "oom" => Some(Self::Oom),
+            "restart" => Some(Self::Restart),
+            _ => None
+        }
+    }
+
+    /// The glyph marking the event on a history.
+    pub fn symbol(&self) -> char {
+        match self {
+            Self::Start => '▶',
+            Self::Stop => '■',
+            Self::Die(_) => '✕',
+            Self::Oom => '!',
+            Self::Restart => '↻',
+            Self::Health(_) => '♥'
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Self::Start => Color::Green,
+            Self::Stop | Self::Die(0) => Color::Yellow,
+            Self::Die(_) | Self::Oom => Color::Red,
+            Self::Restart => Color::Cyan,
+            Self::Health(status) if status == "healthy" => Color::Green,
+            Self::Health(_) => Color::Red
+        }
+    }
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Start => write!(f, "started"),
+            Self::Stop => write!(f, "stopped"),
+            Self::Die(code) => write!(f, "died (exit {code})"),
+            Self::Oom => write!(f, "ran out of memory"),
+            Self::Restart => write!(f, "restarted"),
+            Self::Health(status) => write!(f, "health: {status}")
+        }
+    }
+}
+
+/// Something that happened to a container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// When it happened, in seconds since the Unix epoch.
+    pub at: u64,
+    pub id: String,
+    pub name: String,
+    pub kind: EventKind
+}
+
+/// The most recent events, oldest first.
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    events: VecDeque<Event>,
+    capacity: usize
+}
+
+impl EventLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::with_capacity(capacity),
+            capacity
+        }
+    }
+
+    /// Appends an event, evicting the oldest one once full.
+    pub fn push(&mut self, event: Event) {
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    /// The events from oldest to newest.
+    pub fn events(&self) -> impl DoubleEndedIterator<Item = &Event> + ExactSizeIterator { self.events.iter() }
+}
+
+impl Default for EventLog {
+    fn default() -> Self { Self::new(EVENT_LOG_CAPACITY) }
+}
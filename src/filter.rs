@@ -0,0 +1,145 @@
+use crate::{data::ContainerSample, engine::ContainerSummary};
+use regex::Regex;
+use std::collections::HashMap;
+
+/// A container name pattern, either a `/regex/` or a glob where `*` matches any run of characters and `?` a single one.
+#[derive(Debug, Clone)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        let regex = match pattern.strip_prefix('/').and_then(|p| p.strip_suffix('/')) {
+            Some(regex) => regex.to_string(),
+            None => glob(pattern)
+        };
+
+        Regex::new(&regex)
+            .map(Self)
+            .map_err(|e| format!("invalid pattern {pattern:?}: {e}"))
+    }
+
+    pub fn matches(&self, name: &str) -> bool { self.0.is_match(name) }
+}
+
+/// Translates a glob into an anchored regex.
+fn glob(pattern: &str) -> String {
+    let body: String = pattern
+        .chars()
+        .map(|c| match c {
+            '*' => ".*".to_string(),
+            '?' => ".".to_string(),
+            c => regex::escape(&c.to_string())
+        })
+        .collect();
+
+    format!("^{body}$")
+}
+
+/// A `key=value` label filter, a bare `key` matches any value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub key: String,
+    pub value: Option<String>
+}
+
+impl Label {
+    pub fn parse(label: &str) -> Result<Self, String> {
+        let (key, value) = match label.split_once('=') {
+            Some((key, value)) => (key, Some(value.to_string())),
+            None => (label, None)
+        };
+
+        if key.is_empty() {
+            return Err(format!("invalid label {label:?}: the key is empty"));
+        }
+
+        Ok(Self {
+            key: key.to_string(),
+            value
+        })
+    }
+
+    pub fn matches(&self, labels: &HashMap<String, String>) -> bool {
+        labels
+            .get(&self.key)
+            .is_some_and(|value| self.value.as_ref().is_none_or(|v| v == value))
+    }
+}
+
+/// Which containers to show. Containers listed by name or ID and name patterns add up,
+/// while labels, images and exclusions narrow things down. An empty filter shows everything.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// Names or ID prefixes given as arguments.
+    pub containers: Vec<String>,
+    pub patterns: Vec<Pattern>,
+    /// Every label has to match.
+    pub labels: Vec<Label>,
+    /// Any image may match, either `repository` for every tag, `repository:tag` or an image ID.
+    pub images: Vec<String>,
+    pub excludes: Vec<Pattern>,
+    /// Include stopped containers too.
+    pub all: bool
+}
+
+impl Filter {
+    pub fn new(containers: Vec<String>) -> Self {
+        Self {
+            containers,
+            ..Self::default()
+        }
+    }
+
+    /// The flags used that look at labels, images or container states, which only the engine APIs report.
+    pub fn metadata_flags(&self) -> Vec<&'static str> {
+        [
+            ("--label", !self.labels.is_empty()),
+            ("--image", !self.images.is_empty()),
+            ("--all", self.all)
+        ]
+        .into_iter()
+        .filter_map(|(flag, used)| used.then_some(flag))
+        .collect()
+    }
+
+    /// Whether a listed container passes every filter.
+    pub fn matches(&self, summary: &ContainerSummary) -> bool {
+        let empty = HashMap::new();
+        let labels = summary.labels.as_ref().unwrap_or(&empty);
+
+        self.matches_name(summary.name(), |q| summary.matches(q))
+            && self.labels.iter().all(|l| l.matches(labels))
+            && (self.images.is_empty() || self.images.iter().any(|i| matches_image(summary, i)))
+    }
+
+    /// Whether a sample passes the name filters, the only ones a sample has enough to go by.
+    pub fn matches_sample(&self, sample: &ContainerSample) -> bool { self.matches_name(&sample.name, |q| sample.matches(q)) }
+
+    fn matches_name(&self, name: &str, is: impl Fn(&str) -> bool) -> bool {
+        let included = (self.containers.is_empty() && self.patterns.is_empty())
+            || self.containers.iter().any(|q| is(q))
+            || self.patterns.iter().any(|p| p.matches(name));
+
+        included && !self.excludes.iter().any(|p| p.matches(name))
+    }
+}
+
+fn matches_image(summary: &ContainerSummary, query: &str) -> bool {
+    let image = summary.image.as_str();
+
+    // A tag is whatever follows the last colon, unless that colon belongs to a registry port
+    let repository = match image.rsplit_once(':') {
+        Some((repository, tag)) if !tag.contains('/') => repository,
+        _ => image
+    };
+
+    let id = query.trim_start_matches("sha256:");
+
+    image == query
+        || repository == query
+        || (!id.is_empty()
+            && summary
+                .image_id
+                .trim_start_matches("sha256:")
+                .starts_with(id))
+}
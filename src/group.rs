@@ -0,0 +1,146 @@
+use crate::state::{ContainerState, CpuMode};
+
+/// The label Docker Compose puts the project name in.
+pub const COMPOSE_PROJECT: &str = "com.docker.compose.project";
+/// The label Docker Compose puts the service name in.
+pub const COMPOSE_SERVICE: &str = "com.docker.compose.service";
+/// The label Docker Compose numbers the replicas of a service with.
+pub const COMPOSE_NUMBER: &str = "com.docker.compose.container-number";
+
+/// How to bucket containers into sections.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GroupBy {
+    /// By Docker Compose project, naming rows after their service.
+    Compose,
+    /// By the value of a label.
+    Label(String),
+    /// By image, tag included.
+    Image
+}
+
+impl GroupBy {
+    /// Parses `compose`, `image` or `label:<key>`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "compose" => Ok(Self::Compose),
+            "image" => Ok(Self::Image),
+            label => match label.strip_prefix("label:") {
+                Some("") => Err("missing label key, expected label:<key>".to_string()),
+                Some(key) => Ok(Self::Label(key.to_string())),
+                None => Err(format!("unknown grouping {value:?}, expected compose, image or label:<key>"))
+            }
+        }
+    }
+
+    /// What to call the section of the containers without a key.
+    pub fn ungrouped(&self) -> String {
+        match self {
+            Self::Compose => "no project".to_string(),
+            Self::Label(key) => format!("no {key} label"),
+            Self::Image => "no image".to_string()
+        }
+    }
+
+    /// The section a container belongs to, `None` if it has none.
+    pub fn key(&self, state: &ContainerState) -> Option<String> {
+        let metadata = &state.metadata;
+        match self {
+            Self::Compose => metadata.labels.get(COMPOSE_PROJECT).cloned(),
+            Self::Label(key) => metadata.labels.get(key).cloned(),
+            Self::Image => Some(metadata.image.clone()).filter(|i| !i.is_empty())
+        }
+    }
+
+    /// What to call a container within its section, `None` to keep its name.
+    pub fn title(&self, state: &ContainerState) -> Option<String> {
+        match self {
+            Self::Compose => {
+                let labels = &state.metadata.labels;
+                let service = labels.get(COMPOSE_SERVICE)?;
+                match labels.get(COMPOSE_NUMBER).map(String::as_str) {
+                    None | Some("1") => Some(service.clone()),
+                    Some(number) => Some(format!("{service} #{number}"))
+                }
+            }
+            Self::Label(_) | Self::Image => None
+        }
+    }
+}
+
+/// Summed stats of every container in a group.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Totals {
+    pub cpu_perc: f64,
+    pub mem_usage: u64,
+    pub net_rx: f64,
+    pub net_tx: f64
+}
+
+/// A section of containers sharing a key.
+#[derive(Debug)]
+pub struct Group<'a> {
+    /// `None` for the containers without a key.
+    pub key: Option<String>,
+    pub containers: Vec<&'a ContainerState>
+}
+
+impl Totals {
+    /// The largest of each total, for scaling bars against.
+    pub fn max(self, other: Self) -> Self {
+        Self {
+            cpu_perc: self.cpu_perc.max(other.cpu_perc),
+            mem_usage: self.mem_usage.max(other.mem_usage),
+            net_rx: self.net_rx.max(other.net_rx),
+            net_tx: self.net_tx.max(other.net_tx)
+        }
+    }
+}
+
+impl Group<'_> {
+    /// Sums the containers still running, the exited ones linger with their last sample.
+    /// CPU usage adds up the way `cpu_mode` counts it.
+    pub fn totals(&self, cpu_mode: CpuMode) -> Totals {
+        let running: Vec<&ContainerState> = self
+            .containers
+            .iter()
+            .copied()
+            .filter(|state| state.exited.is_none())
+            .collect();
+        let totals = running
+            .iter()
+            .fold(Totals::default(), |totals, state| Totals {
+                mem_usage: totals.mem_usage + state.sample.mem_usage,
+                net_rx: totals.net_rx + state.rates.net_rx,
+                net_tx: totals.net_tx + state.rates.net_tx,
+                ..totals
+            });
+
+        Totals {
+            cpu_perc: cpu_mode.total(running),
+            ..totals
+        }
+    }
+}
+
+/// Buckets the containers by key, keeping their order within each group.
+/// Groups are sorted by key so they don't move around, the containers without one come last.
+pub fn group<'a>(by: &GroupBy, containers: &'a [ContainerState]) -> Vec<Group<'a>> {
+    let mut groups: Vec<Group<'a>> = Vec::new();
+
+    for state in containers {
+        let key = by.key(state);
+        match groups.iter_mut().find(|g| g.key == key) {
+            Some(group) => group.containers.push(state),
+            None => groups.push(Group {
+                key,
+                containers: vec![state]
+            })
+        }
+    }
+
+    groups.sort_by(|a, b| match (&a.key, &b.key) {
+        (Some(a), Some(b)) => a.cmp(b),
+        (a, b) => b.is_some().cmp(&a.is_some())
+    });
+    groups
+}
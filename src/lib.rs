@@ -1,6 +1,16 @@
+pub mod cgroup;
+pub mod chart;
 pub mod cli;
 pub mod data;
 pub mod display;
+pub mod engine;
 pub mod error;
 pub mod escape;
+pub mod event;
+pub mod filter;
+pub mod group;
+pub mod sort;
+pub mod source;
+pub mod state;
+pub mod tui;
 pub mod utils;
@@ -1,29 +1,43 @@
+mod cgroup;
+mod chart;
 mod cli;
 mod data;
 mod display;
+mod engine;
 mod error;
 mod escape;
+mod event;
+mod filter;
+mod group;
+mod sort;
+mod source;
+mod state;
+mod tui;
 mod utils;
 
-use data::DockerStats;
-use display::StatsDisplay;
+use display::{MemScale, StatsDisplay};
 use error::{AppError, Result};
-use escape::EscapeSequenceCleaner;
+use event::EventLog;
+use filter::Filter;
+use group::GroupBy;
+use sort::{Sort, SortKey};
+use source::Sink;
+use state::{ContainerState, CpuMode};
+use tui::Action;
 use utils::*;
 
 use std::{
-    io::{BufRead, BufReader},
-    process::{Command, Stdio},
     sync::{
         atomic::{AtomicBool, Ordering},
         mpsc::{self, Receiver, TryRecvError},
-        Arc
+        Arc, Mutex
     },
     thread,
     time::{Duration, Instant}
 };
 
 static RUNNING: AtomicBool = AtomicBool::new(true);
+static RESIZED: AtomicBool = AtomicBool::new(false);
 
 fn main() {
     // Setup signal handling for graceful shutdown
@@ -39,14 +53,24 @@ fn main() {
 }
 
 fn setup_signal_handling() -> Result<()> {
-    use signal_hook::{consts::SIGINT, iterator::Signals};
+    use signal_hook::{
+        consts::{SIGINT, SIGWINCH},
+        iterator::Signals
+    };
 
-    let mut signals = Signals::new([SIGINT]).map_err(AppError::IoError)?;
+    let mut signals = Signals::new([SIGINT, SIGWINCH]).map_err(AppError::IoError)?;
 
     thread::spawn(move || {
-        if signals.forever().next().is_some() {
+        for signal in signals.forever() {
+            if signal == SIGWINCH {
+                // Redraw right away instead of waiting for the next frame
+                RESIZED.store(true, Ordering::SeqCst);
+                continue;
+            }
+
             println!("\nReceived Ctrl+C, shutting down gracefully...");
             RUNNING.store(false, Ordering::SeqCst);
+            break;
         }
     });
 
@@ -56,31 +80,95 @@ fn setup_signal_handling() -> Result<()> {
 fn run_app() -> Result<()> {
     let matches = cli::args().get_matches();
     let (compact, full) = (get_flag(&matches, "compact"), get_flag(&matches, "full"));
-    let width = get_terminal_width();
-
-    println!("Starting Docker stats monitor...");
-    println!("Press Ctrl+C to exit");
+    let interactive = get_flag(&matches, "interactive");
+
+    let queries: Vec<String> = values(&matches, "CONTAINER");
+    let kind = matches
+        .get_one::<String>("source")
+        .map(String::as_str)
+        .unwrap_or("auto");
+    let chart = matches.get_one::<String>("chart").cloned();
+    let cpu_mode = matches
+        .get_one::<String>("cpu-mode")
+        .and_then(|mode| CpuMode::parse(mode))
+        .unwrap_or_default();
+    let sort = matches
+        .get_one::<String>("sort")
+        .and_then(|key| SortKey::parse(key))
+        .map_or_else(Sort::default, |key| Sort::new(key, get_flag(&matches, "reverse")))
+        .with_cpu_mode(cpu_mode);
+
+    // Charting a single container only needs its own stats
+    let queries = match &chart {
+        Some(container) if queries.is_empty() => vec![container.clone()],
+        _ => queries
+    };
+    let filter = Filter {
+        patterns: values(&matches, "filter"),
+        labels: values(&matches, "label"),
+        images: values(&matches, "image"),
+        excludes: values(&matches, "exclude"),
+        all: get_flag(&matches, "all"),
+        ..Filter::new(queries)
+    };
+
+    // Groups are made from labels and events come from their own stream, which only the runtime APIs have
+    let group_by = matches.get_one::<GroupBy>("group-by").cloned();
+    let events = get_flag(&matches, "events");
+    let mut flags = filter.metadata_flags();
+    flags.extend(
+        [("--group-by", group_by.is_some()), ("--events", events)]
+            .into_iter()
+            .filter_map(|(flag, used)| used.then_some(flag))
+    );
+
+    let mut source = source::select(kind, filter)?;
+    source::require_metadata(source.as_ref(), &flags)?;
+
+    println!("Starting Docker stats monitor ({})...", source.name());
+    println!("Press {} to exit", if interactive { "q" } else { "Ctrl+C" });
 
     // Channel for communication between threads
-    let (stats_sender, _stats_receiver) = mpsc::channel::<Vec<DockerStats>>();
     let (heartbeat_sender, heartbeat_receiver) = mpsc::channel::<()>();
 
     // Shared containers data
-    let containers = Arc::new(std::sync::Mutex::new(Vec::<DockerStats>::new()));
-    let display = Arc::new(StatsDisplay::new(width, compact, full));
+    let containers = Arc::new(Mutex::new(Vec::<ContainerState>::new()));
+    let terminal = interactive.then(tui::Terminal::enter).transpose()?;
+    let mem_scale = matches
+        .get_one::<String>("scale")
+        .and_then(|scale| MemScale::parse(scale))
+        .unwrap_or_default();
+    let display = StatsDisplay::new(get_terminal_width(), compact, full)
+        .with_sort(sort)
+        .with_cpu_mode(cpu_mode)
+        .with_mem_scale(mem_scale);
+    let display = match (&chart, interactive) {
+        (Some(container), _) => display.with_chart(container),
+        (None, true) => display.interactive(),
+        (None, false) => display
+    };
+    let display = match group_by {
+        Some(group_by) => display.with_group_by(group_by),
+        None => display
+    };
+    let display = if get_flag(&matches, "all") { display.with_summary() } else { display };
+    let event_log = Arc::new(Mutex::new(EventLog::default()));
+    let display = if events { display.with_events(event_log.clone()) } else { display };
 
     // Spawn display thread
     let display_containers = containers.clone();
-    let display_handle = display.clone();
-    let display_thread = thread::spawn(move || display_loop(heartbeat_receiver, display_containers, display_handle));
+    let grace = Duration::from_secs(matches.get_one::<u64>("grace").copied().unwrap_or(30));
+    let display_thread = thread::spawn(move || display_loop(heartbeat_receiver, display_containers, display, interactive, grace));
 
-    // Spawn Docker stats reader thread
-    let reader_containers = containers.clone();
-    let reader_thread = thread::spawn(move || docker_stats_reader(matches, stats_sender, heartbeat_sender, reader_containers));
+    // Spawn the stats reader thread
+    let history = matches.get_one::<usize>("history").copied().unwrap_or(60);
+    let sink = Sink::new(containers.clone(), heartbeat_sender, &RUNNING, history).with_events(event_log);
+    let reader_thread = thread::spawn(move || source.run(&sink));
 
     // Wait for threads to complete
     let reader_result = reader_thread.join();
     let display_result = display_thread.join();
+    drop(terminal);
 
     // Handle thread results
     match (reader_result, display_result) {
@@ -95,115 +183,82 @@ fn run_app() -> Result<()> {
     }
 }
 
-fn docker_stats_reader(
-    matches: clap::ArgMatches,
-    _stats_sender: mpsc::Sender<Vec<DockerStats>>,
-    heartbeat_sender: mpsc::Sender<()>,
-    containers: Arc<std::sync::Mutex<Vec<DockerStats>>>
-) -> Result<()> {
-    let mut cmd = Command::new("docker")
-        .args(build_command(matches))
-        .stdout(Stdio::piped())
-        .spawn()
-        .map_err(AppError::from)?;
-
-    let stdout = cmd
-        .stdout
-        .take()
-        .ok_or_else(|| AppError::TerminalError("Failed to get stdout from docker command".to_string()))?;
-
-    let reader = BufReader::new(stdout);
-    let mut escape_cleaner = EscapeSequenceCleaner::new();
-
-    for line_result in reader.lines() {
+/// Draws a frame at a time until the app stops or the source goes away.
+/// Containers that exited or went quiet are shown dimmed, and dropped once they've been gone for `grace`.
+fn display_loop(
+    heartbeat_receiver: Receiver<()>,
+    containers: Arc<Mutex<Vec<ContainerState>>>,
+    mut display: StatsDisplay,
+    interactive: bool,
+    grace: Duration
+) {
+    let mut last_page = Instant::now();
+    let page_duration = Duration::from_secs(5);
+
+    loop {
         if !RUNNING.load(Ordering::SeqCst) {
             break;
         }
 
-        let line = line_result.map_err(AppError::from)?;
-
-        // Send heartbeat
-        let _ = heartbeat_sender.send(());
-
-        // Check for screen clear event
-        if EscapeSequenceCleaner::is_screen_clear_event(&line) {
-            if let Ok(mut guard) = containers.lock() {
-                guard.clear();
+        // Drain the heartbeats, the source only hangs up when it's done
+        loop {
+            match heartbeat_receiver.try_recv() {
+                Ok(()) => continue,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return
             }
         }
 
-        // Process the line
-        if let Some(clean_line) = escape_cleaner.process_line(line) {
-            match serde_json::from_str::<DockerStats>(&clean_line) {
-                Ok(stats) => {
-                    if let Ok(mut containers_guard) = containers.lock() {
-                        // Find existing container by name and update it, or add new one
-                        if let Some(existing) = containers_guard.iter_mut().find(|c| c.name == stats.name) {
-                            *existing = stats;
-                        } else {
-                            containers_guard.push(stats);
-                        }
-                    }
-                }
-                Err(e) => {
-                    // Log parsing errors but don't stop the application
-                    eprintln!("Warning: Failed to parse JSON: {e}");
-                    continue;
-                }
-            }
+        if let Ok(mut guard) = containers.lock() {
+            guard.retain(|c| c.last_seen().elapsed() < grace);
         }
-    }
 
-    // Wait for docker command to finish
-    let status = cmd.wait().map_err(AppError::from)?;
+        // Follow the terminal size, it may have changed since the last frame
+        display.resize(get_terminal_width(), get_terminal_height());
 
-    // If we were interrupted (Ctrl+C), don't treat this as an error
-    if !RUNNING.load(Ordering::SeqCst) {
-        return Ok(());
-    }
+        // Display current stats, flipping through the pages when they don't all fit
+        if let Ok(mut guard) = containers.lock() {
+            display.sort().apply(&mut guard);
+            if !interactive && last_page.elapsed() > page_duration {
+                display.next_page(&guard);
+                last_page = Instant::now();
+            }
+            display.print_stats(&guard);
+        }
 
-    if !status.success() {
-        return Err(AppError::DockerNotRunning);
+        wait_for_frame(&mut display, &containers, interactive);
     }
-
-    Ok(())
 }
 
-fn display_loop(heartbeat_receiver: Receiver<()>, containers: Arc<std::sync::Mutex<Vec<DockerStats>>>, display: Arc<StatsDisplay>) {
-    let mut last_heartbeat = Instant::now();
-    let timeout_duration = Duration::from_secs(3);
+/// Sleeps until the next frame is due to avoid excessive CPU usage.
+/// Wakes up early on resize and, in interactive mode, as soon as a key is handled.
+fn wait_for_frame(display: &mut StatsDisplay, containers: &Mutex<Vec<ContainerState>>, interactive: bool) {
+    let frame = Instant::now();
 
-    loop {
-        if !RUNNING.load(Ordering::SeqCst) {
-            break;
+    while frame.elapsed() < Duration::from_millis(500) && !RESIZED.swap(false, Ordering::SeqCst) {
+        if !interactive {
+            thread::sleep(Duration::from_millis(20));
+            continue;
         }
 
-        // Check for heartbeat
-        match heartbeat_receiver.try_recv() {
-            Ok(()) => {
-                last_heartbeat = Instant::now();
-            }
-            Err(TryRecvError::Empty) => {
-                // No new heartbeat, check if we should timeout
-                if last_heartbeat.elapsed() > timeout_duration {
-                    if let Ok(mut guard) = containers.lock() {
-                        guard.clear();
-                    }
-                    last_heartbeat = Instant::now();
+        let Ok(Some(action)) = tui::next_action(Duration::from_millis(20)) else {
+            continue;
+        };
+
+        match action {
+            Action::Up | Action::Down => {
+                let delta = if action == Action::Up { -1 } else { 1 };
+                if let Ok(guard) = containers.lock() {
+                    display.select(&guard, delta);
                 }
             }
-            Err(TryRecvError::Disconnected) => {
-                // Sender disconnected, exit
-                break;
-            }
+            Action::Open => display.open(),
+            Action::Back => display.close(),
+            Action::Sort => display.cycle_sort(),
+            Action::Reverse => display.reverse_sort(),
+            Action::Quit => RUNNING.store(false, Ordering::SeqCst),
+            Action::Redraw => {}
         }
-
-        // Display current stats
-        if let Ok(guard) = containers.lock() {
-            display.print_stats(&guard);
-        }
-
-        // Sleep briefly to avoid excessive CPU usage
-        thread::sleep(Duration::from_millis(500));
+        return;
     }
 }
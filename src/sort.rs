@@ -0,0 +1,97 @@
+use crate::state::{ContainerState, CpuMode};
+use std::cmp::Ordering;
+
+/// The values accepted by `--sort`, in the order the interactive hotkey cycles through them.
+pub const SORT_KEYS: [&str; 6] = ["cpu", "mem", "net", "io", "name", "id"];
+
+/// What to order the containers by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Cpu,
+    Mem,
+    Net,
+    Io,
+    Name,
+    Id
+}
+
+impl SortKey {
+    const ALL: [SortKey; 6] = [Self::Cpu, Self::Mem, Self::Net, Self::Io, Self::Name, Self::Id];
+
+    pub fn parse(key: &str) -> Option<Self> {
+        SORT_KEYS
+            .iter()
+            .position(|k| *k == key)
+            .map(|i| Self::ALL[i])
+    }
+
+    pub fn name(&self) -> &'static str { SORT_KEYS[self.index()] }
+
+    /// The key after this one, wrapping around.
+    pub fn next(&self) -> Self { Self::ALL[(self.index() + 1) % Self::ALL.len()] }
+
+    fn index(&self) -> usize { Self::ALL.iter().position(|k| k == self).unwrap_or(0) }
+
+    /// Metrics list the busiest containers first, names and IDs go alphabetically.
+    fn descending(&self) -> bool { !matches!(self, Self::Name | Self::Id) }
+}
+
+/// A sort key and direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sort {
+    pub key: SortKey,
+    /// Flips the key's natural direction.
+    pub reverse: bool,
+    /// How CPU usage is counted when sorting by it.
+    pub cpu_mode: CpuMode
+}
+
+impl Sort {
+    pub fn new(key: SortKey, reverse: bool) -> Self {
+        Self {
+            key,
+            reverse,
+            cpu_mode: CpuMode::default()
+        }
+    }
+
+    pub fn with_cpu_mode(mut self, cpu_mode: CpuMode) -> Self {
+        self.cpu_mode = cpu_mode;
+        self
+    }
+
+    /// Whether the largest values come first.
+    pub fn descending(&self) -> bool { self.key.descending() != self.reverse }
+
+    /// Orders the containers in place. Ties fall back to name then ID, so rows don't swap places between frames.
+    pub fn apply(&self, containers: &mut [ContainerState]) {
+        containers.sort_by(|a, b| {
+            let order = self.compare(a, b);
+            let order = if self.reverse { order.reverse() } else { order };
+            order
+                .then_with(|| a.sample.name.cmp(&b.sample.name))
+                .then_with(|| a.id().cmp(b.id()))
+        });
+    }
+
+    /// Compares two containers in the key's natural direction.
+    fn compare(&self, a: &ContainerState, b: &ContainerState) -> Ordering {
+        let metric = |state: &ContainerState| match self.key {
+            SortKey::Cpu => self.cpu_mode.perc(state),
+            SortKey::Mem => state.sample.mem_usage as f64,
+            SortKey::Net => state.rates.net_rx + state.rates.net_tx,
+            SortKey::Io => state.rates.block_read + state.rates.block_write,
+            SortKey::Name | SortKey::Id => 0.0
+        };
+
+        match self.key {
+            SortKey::Name => a.sample.name.cmp(&b.sample.name),
+            SortKey::Id => a.id().cmp(b.id()),
+            _ => metric(b).total_cmp(&metric(a))
+        }
+    }
+}
+
+impl Default for Sort {
+    fn default() -> Self { Self::new(SortKey::Name, false) }
+}
@@ -0,0 +1,557 @@
+use crate::{
+    cgroup::{self, CgroupReader},
+    data::{ContainerSample, Metadata, PodmanStats},
+    engine::{ContainerDetails, EngineClient},
+    error::{AppError, Result},
+    escape::EscapeSequenceCleaner,
+    event::{Event, EventKind, EventLog},
+    filter::Filter,
+    state::ContainerState,
+    utils::{build_command, on_path}
+};
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, BufRead, BufReader},
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+        Arc, Mutex
+    },
+    thread,
+    time::{Duration, Instant}
+};
+
+/// The `--source` values, `auto` picks the best available one.
+pub const SOURCES: [&str; 6] = ["auto", "docker", "docker-cli", "podman", "podman-cli", "cgroup"];
+
+/// How many listing rounds of the engine source pass between inspecting containers again.
+const INSPECT_EVERY: u64 = 5;
+
+/// A backend that collects container stats and publishes them to a [`Sink`].
+pub trait StatsSource: Send {
+    /// A short, human readable name for the backend.
+    fn name(&self) -> &'static str;
+
+    /// Collects samples until the backend runs dry or the sink asks to stop.
+    fn run(&mut self, sink: &Sink) -> Result<()>;
+
+    /// Whether the backend knows container labels, images, states and events, see [`require_metadata`].
+    fn metadata(&self) -> bool { false }
+}
+
+/// Fails with a usage error when any of the given flags are used with a source that can't back them.
+pub fn require_metadata(source: &dyn StatsSource, flags: &[&str]) -> Result<()> {
+    let (flags, verb) = match flags {
+        _ if source.metadata() => return Ok(()),
+        [] => return Ok(()),
+        [flag] => (flag.to_string(), "needs"),
+        [rest @ .., last] => (format!("{} and {last}", rest.join(", ")), "need")
+    };
+
+    Err(AppError::Usage(format!(
+        "{flags} {verb} the Docker or Podman API, which the {} source can't use",
+        source.name()
+    )))
+}
+
+/// Builds the source requested through `--source`, restricted to the containers passing the filter.
+pub fn select(kind: &str, filter: Filter) -> Result<Box<dyn StatsSource>> {
+    let source: Box<dyn StatsSource> = match kind {
+        "auto" => detect(filter)?,
+        "docker" => Box::new(EngineSource::new("Docker Engine API", EngineClient::from_env(), filter)),
+        "docker-cli" => Box::new(CliSource::new("docker", filter)),
+        "podman" => Box::new(EngineSource::new("Podman API", EngineClient::podman(), filter)),
+        "podman-cli" => Box::new(PodmanCliSource::new(filter)),
+        "cgroup" => Box::new(CgroupSource::new(
+            CgroupReader::new(cgroup::DEFAULT_ROOT).with_configs(cgroup::DOCKER_CONTAINERS),
+            filter
+        )),
+        other => return Err(AppError::TerminalError(format!("Unknown stats source: {other}")))
+    };
+
+    Ok(source)
+}
+
+/// Picks the first runtime that looks available, preferring sockets over spawning CLIs,
+/// and reading cgroups directly when there's no runtime at all.
+fn detect(filter: Filter) -> Result<Box<dyn StatsSource>> {
+    let (docker, podman) = (EngineClient::from_env(), EngineClient::podman());
+    let reader = CgroupReader::new(cgroup::DEFAULT_ROOT).with_configs(cgroup::DOCKER_CONTAINERS);
+
+    Ok(if docker.socket().exists() {
+        Box::new(EngineSource::new("Docker Engine API", docker, filter))
+    } else if podman.socket().exists() {
+        Box::new(EngineSource::new("Podman API", podman, filter))
+    } else if on_path("docker") {
+        Box::new(CliSource::new("docker", filter))
+    } else if on_path("podman") {
+        Box::new(PodmanCliSource::new(filter))
+    } else if reader.readable() {
+        Box::new(CgroupSource::new(reader, filter))
+    } else {
+        let message = format!(
+            "no Docker or Podman socket or CLI found, and no cgroup hierarchy to read at {}",
+            cgroup::DEFAULT_ROOT
+        );
+        return Err(AppError::IoError(io::Error::new(io::ErrorKind::NotFound, message)));
+    })
+}
+
+/// How long the name of a removed container is remembered, to tell a recreated container from a new one.
+const RECREATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// The shared state sources publish into, read by the display loop.
+#[derive(Clone)]
+pub struct Sink {
+    containers: Arc<Mutex<Vec<ContainerState>>>,
+    heartbeat: Sender<()>,
+    running: &'static AtomicBool,
+    history: usize,
+    /// Names of the containers removed lately, and when.
+    departed: Arc<Mutex<HashMap<String, Instant>>>,
+    events: Arc<Mutex<EventLog>>
+}
+
+impl Sink {
+    /// Creates a sink whose containers remember the last `history` seconds of stats.
+    pub fn new(containers: Arc<Mutex<Vec<ContainerState>>>, heartbeat: Sender<()>, running: &'static AtomicBool, history: usize) -> Self {
+        Self {
+            containers,
+            heartbeat,
+            running,
+            history,
+            departed: Arc::default(),
+            events: Arc::default()
+        }
+    }
+
+    /// Logs the events into a shared log, for the display to show.
+    pub fn with_events(mut self, events: Arc<Mutex<EventLog>>) -> Self {
+        self.events = events;
+        self
+    }
+
+    pub fn is_running(&self) -> bool { self.running.load(Ordering::SeqCst) }
+
+    /// Signals the display loop that the source is alive.
+    pub fn heartbeat(&self) { let _ = self.heartbeat.send(()); }
+
+    /// Updates a container's row, or adds one if it's the first sample.
+    /// Rows are keyed by ID, so a renamed container keeps its history while a recreated one starts over.
+    pub fn publish(&self, stats: ContainerSample) {
+        self.heartbeat();
+
+        if let Ok(mut guard) = self.containers.lock() {
+            if let Some(existing) = guard.iter_mut().find(|c| c.id() == stats.id) {
+                existing.update(stats);
+                return;
+            }
+
+            // A new ID under a known name means the container was recreated, e.g. by `docker compose up --force-recreate`
+            let before = guard.len();
+            guard.retain(|c| c.sample.name != stats.name);
+            let recreated = guard.len() < before || self.departed(&stats.name);
+
+            let mut state = ContainerState::new(stats, self.history);
+            state.recreated = recreated.then(Instant::now);
+            guard.push(state);
+        }
+    }
+
+    /// Attaches metadata to a container's row, if it has one yet.
+    pub fn describe(&self, id: &str, metadata: Metadata) {
+        if let Ok(mut guard) = self.containers.lock() {
+            if let Some(existing) = guard.iter_mut().find(|c| c.id() == id) {
+                existing.describe(metadata);
+            }
+        }
+    }
+
+    /// Logs an event and marks it on the container's history. A container that died is marked as exited right away,
+    /// rather than when its stats stop.
+    pub fn record(&self, event: Event) {
+        if let Ok(mut guard) = self.containers.lock() {
+            if let Some(state) = guard.iter_mut().find(|c| c.id() == event.id) {
+                state.record(event.kind.clone());
+            }
+        }
+
+        if matches!(event.kind, EventKind::Die(_)) {
+            self.remove(&event.id);
+        }
+
+        if let Ok(mut events) = self.events.lock() {
+            events.push(event);
+        }
+    }
+
+    /// Marks a container as exited, its row stays until the display's grace period runs out.
+    pub fn remove(&self, id: &str) { self.exit_where(|c| c.id() == id); }
+
+    /// Marks every container that isn't in `ids` as exited.
+    pub fn retain(&self, ids: &HashSet<String>) { self.exit_where(|c| !ids.contains(c.id())); }
+
+    /// Marks the matching rows as exited, remembering their names for a while.
+    fn exit_where(&self, exited: impl Fn(&ContainerState) -> bool) {
+        let Ok(mut guard) = self.containers.lock() else { return };
+        let Ok(mut departed) = self.departed.lock() else { return };
+
+        departed.retain(|_, at| at.elapsed() < RECREATE_WINDOW);
+        for state in guard.iter_mut().filter(|c| c.exited.is_none() && exited(c)) {
+            departed.insert(state.sample.name.clone(), Instant::now());
+            state.exited = Some(Instant::now());
+        }
+    }
+
+    /// Whether a container with this name was removed lately.
+    fn departed(&self, name: &str) -> bool {
+        self.departed
+            .lock()
+            .is_ok_and(|d| d.get(name).is_some_and(|at| at.elapsed() < RECREATE_WINDOW))
+    }
+
+    /// Updates all rows with a full snapshot, marking the containers missing from it as exited.
+    pub fn publish_all(&self, stats: Vec<ContainerSample>) {
+        self.heartbeat();
+        self.retain(&stats.iter().map(|s| s.id.clone()).collect());
+        stats.into_iter().for_each(|s| self.publish(s));
+    }
+
+    /// Sleeps in small steps so Ctrl+C isn't held up, returns whether we should keep going.
+    pub fn wait(&self, duration: Duration) -> bool {
+        let step = Duration::from_millis(100);
+        let mut waited = Duration::ZERO;
+
+        while waited < duration && self.is_running() {
+            thread::sleep(step);
+            waited += step;
+        }
+
+        self.is_running()
+    }
+}
+
+/// Reads stats from the Docker Engine API (or a compatible one), one stream per container.
+pub struct EngineSource {
+    name: &'static str,
+    client: EngineClient,
+    filter: Filter
+}
+
+impl EngineSource {
+    pub fn new(name: &'static str, client: EngineClient, filter: Filter) -> Self { Self { name, client, filter } }
+}
+
+impl StatsSource for EngineSource {
+    fn name(&self) -> &'static str { self.name }
+
+    fn metadata(&self) -> bool { true }
+
+    fn run(&mut self, sink: &Sink) -> Result<()> {
+        let streaming = Arc::new(Mutex::new(HashSet::<String>::new()));
+        let (client, filter, events_sink) = (self.client.clone(), self.filter.clone(), sink.clone());
+        thread::spawn(move || watch_events(client, filter, events_sink));
+
+        let mut details: HashMap<String, ContainerDetails> = HashMap::new();
+
+        for round in 0.. {
+            // Inspecting is a request per container, so it's done less often than listing
+            let inspect = round % INSPECT_EVERY == 0;
+            let mut listed = HashSet::new();
+
+            // Discover containers periodically so the ones started later are picked up too.
+            // Past the first round, a failed listing is retried, the rows go stale in the meantime
+            let summaries = match self.client.list_containers(self.filter.all) {
+                Ok(summaries) => summaries,
+                Err(e) if round == 0 => return Err(e),
+                Err(e) => {
+                    eprintln!("Warning: Failed to list containers: {e}");
+                    if !sink.wait(Duration::from_secs(2)) {
+                        break;
+                    }
+                    continue;
+                }
+            };
+
+            for summary in summaries {
+                if !self.filter.matches(&summary) {
+                    continue;
+                }
+                listed.insert(summary.id.clone());
+
+                // A container that changed state, e.g. exited, has new details too
+                let changed = details
+                    .get(&summary.id)
+                    .is_none_or(|d| d.state.status != summary.state);
+                if inspect || changed {
+                    if let Ok(inspected) = self.client.inspect_container(&summary.id) {
+                        details.insert(summary.id.clone(), inspected);
+                    }
+                }
+
+                // Refresh the metadata of the containers already shown, new ones get theirs with the first sample
+                let mut metadata = Metadata::from(&summary);
+                if let Some(details) = details.get(&summary.id) {
+                    details.fill(&mut metadata);
+                }
+                // Stopped containers have no stats to stream, they're listed with empty ones
+                if summary.is_stopped() {
+                    let stats = ContainerSample::new(&summary.id, summary.name(), 0.0, (0, 0), (0, 0), (0, 0));
+                    sink.publish(stats);
+                    sink.describe(&summary.id, metadata);
+                    continue;
+                }
+                sink.describe(&summary.id, metadata.clone());
+
+                let is_new = streaming
+                    .lock()
+                    .map(|mut s| s.insert(summary.id.clone()))
+                    .unwrap_or(false);
+                if is_new {
+                    let (client, sink, streaming) = (self.client.clone(), sink.clone(), streaming.clone());
+                    thread::spawn(move || stream_container(client, summary.id, metadata, sink, streaming));
+                }
+            }
+
+            details.retain(|id, _| listed.contains(id));
+
+            if !sink.wait(Duration::from_secs(2)) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Follows the container events, reconnecting when the stream drops, until the sink stops.
+fn watch_events(client: EngineClient, filter: Filter, sink: Sink) {
+    while sink.is_running() {
+        if let Ok(stream) = client.stream_events() {
+            for message in stream {
+                let Ok(message) = message else { break };
+                if !sink.is_running() {
+                    return;
+                }
+
+                if let Some(event) = message
+                    .event()
+                    .filter(|_| filter.matches(&message.summary()))
+                {
+                    sink.record(event);
+                }
+            }
+        }
+
+        if !sink.wait(Duration::from_secs(2)) {
+            break;
+        }
+    }
+}
+
+fn stream_container(client: EngineClient, id: String, metadata: Metadata, sink: Sink, streaming: Arc<Mutex<HashSet<String>>>) {
+    if let Ok(stream) = client.stream_stats(&id) {
+        let mut metadata = Some(metadata);
+
+        for stats in stream {
+            if !sink.is_running() {
+                break;
+            }
+
+            match stats {
+                Ok(stats) => {
+                    sink.publish(ContainerSample::from(stats));
+                    if let Some(metadata) = metadata.take() {
+                        sink.describe(&id, metadata);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Warning: Failed to read stats: {e}");
+                    break;
+                }
+            }
+        }
+    }
+
+    // The stream ends when the container stops
+    sink.remove(&id);
+    if let Ok(mut guard) = streaming.lock() {
+        guard.remove(&id);
+    }
+}
+
+/// Reads stats by spawning `<program> stats --format json` and scraping its output.
+/// Every container is streamed and filtered here, so the ones started later show up too.
+pub struct CliSource {
+    program: &'static str,
+    args: Vec<String>,
+    filter: Filter
+}
+
+impl CliSource {
+    pub fn new(program: &'static str, filter: Filter) -> Self {
+        Self {
+            program,
+            args: build_command(Vec::new()),
+            filter
+        }
+    }
+}
+
+impl StatsSource for CliSource {
+    fn name(&self) -> &'static str { "docker stats" }
+
+    fn run(&mut self, sink: &Sink) -> Result<()> {
+        let mut cmd = Command::new(self.program)
+            .args(&self.args)
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(AppError::from)?;
+
+        let stdout = cmd
+            .stdout
+            .take()
+            .ok_or_else(|| AppError::TerminalError(format!("Failed to get stdout from {} command", self.program)))?;
+
+        let reader = BufReader::new(stdout);
+        let mut escape_cleaner = EscapeSequenceCleaner::new();
+        let mut frame = HashSet::<String>::new();
+
+        for line_result in reader.lines() {
+            if !sink.is_running() {
+                break;
+            }
+
+            let line = line_result.map_err(AppError::from)?;
+
+            // Send heartbeat
+            sink.heartbeat();
+
+            // A screen clear starts a new frame, the containers the last one didn't list have exited
+            if EscapeSequenceCleaner::is_screen_clear_event(&line) && !frame.is_empty() {
+                sink.retain(&frame);
+                frame.clear();
+            }
+
+            // Process the line
+            if let Some(clean_line) = escape_cleaner.process_line(line) {
+                match serde_json::from_str::<ContainerSample>(&clean_line) {
+                    Ok(stats) if self.filter.matches_sample(&stats) => {
+                        frame.insert(stats.id.clone());
+                        sink.publish(stats);
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        // Log parsing errors but don't stop the application
+                        eprintln!("Warning: Failed to parse JSON: {e}");
+                        continue;
+                    }
+                }
+            }
+        }
+
+        // Wait for the command to finish
+        let status = cmd.wait().map_err(AppError::from)?;
+
+        // If we were interrupted (Ctrl+C), don't treat this as an error
+        if !sink.is_running() {
+            return Ok(());
+        }
+
+        if !status.success() {
+            return Err(AppError::DockerNotRunning);
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads stats by polling `podman stats --no-stream`, as its streaming output is meant for humans.
+pub struct PodmanCliSource {
+    filter: Filter
+}
+
+impl PodmanCliSource {
+    pub fn new(filter: Filter) -> Self { Self { filter } }
+}
+
+impl StatsSource for PodmanCliSource {
+    fn name(&self) -> &'static str { "podman stats" }
+
+    fn run(&mut self, sink: &Sink) -> Result<()> {
+        loop {
+            let output = Command::new("podman")
+                .args(["stats", "--no-stream", "--format", "json"])
+                .output()
+                .map_err(AppError::from)?;
+
+            if !sink.is_running() {
+                return Ok(());
+            }
+
+            if !output.status.success() {
+                return Err(AppError::CommandFailed(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+            }
+
+            // Containers missing from this round have stopped
+            let stats = parse_podman_stats(&output.stdout)?;
+            sink.publish_all(
+                stats
+                    .into_iter()
+                    .map(ContainerSample::from)
+                    .filter(|s| self.filter.matches_sample(s))
+                    .collect()
+            );
+
+            if !sink.wait(Duration::from_secs(1)) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Parses the output of `podman stats --format json`, which is empty or `null` when nothing runs.
+pub fn parse_podman_stats(output: &[u8]) -> Result<Vec<PodmanStats>> {
+    if output.iter().all(u8::is_ascii_whitespace) {
+        return Ok(Vec::new());
+    }
+
+    Ok(serde_json::from_slice::<Option<Vec<PodmanStats>>>(output)?.unwrap_or_default())
+}
+
+/// Reads stats straight from the cgroup hierarchy, for hosts where no runtime is reachable.
+pub struct CgroupSource {
+    reader: CgroupReader,
+    filter: Filter
+}
+
+impl CgroupSource {
+    pub fn new(reader: CgroupReader, filter: Filter) -> Self { Self { reader, filter } }
+}
+
+impl StatsSource for CgroupSource {
+    fn name(&self) -> &'static str { "cgroups" }
+
+    fn run(&mut self, sink: &Sink) -> Result<()> {
+        if !self.reader.root().is_dir() {
+            let message = format!("cgroup hierarchy not found at {}", self.reader.root().display());
+            return Err(AppError::IoError(io::Error::new(io::ErrorKind::NotFound, message)));
+        }
+
+        loop {
+            let stats = self
+                .reader
+                .read()
+                .into_iter()
+                .filter(|s| self.filter.matches_sample(s))
+                .collect();
+
+            sink.publish_all(stats);
+
+            if !sink.wait(Duration::from_secs(1)) {
+                return Ok(());
+            }
+        }
+    }
+}
@@ -0,0 +1,410 @@
+use crate::{
+    data::{ContainerSample, Metadata},
+    engine,
+    event::EventKind,
+    utils::host_cpus
+};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant}
+};
+
+/// How long a container can go without a sample before it's shown as stale.
+pub const STALE_AFTER: Duration = Duration::from_secs(5);
+
+/// How long counters may repeat before they mean no traffic. Daemons refresh them about once a second,
+/// while the docker CLI reprints them twice as often.
+pub const RATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many restarts within [`RESTART_LOOP_WINDOW`] make a crash loop.
+pub const RESTART_LOOP_COUNT: usize = 3;
+
+/// How far back restarts count towards a crash loop.
+pub const RESTART_LOOP_WINDOW: Duration = Duration::from_secs(300);
+
+/// Per-second rates derived from two consecutive samples of the cumulative counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Rates {
+    pub net_rx: f64,
+    pub net_tx: f64,
+    pub block_read: f64,
+    pub block_write: f64
+}
+
+impl Rates {
+    /// Computes the rates between two samples, `None` if no time passed between them.
+    pub fn between(previous: &ContainerSample, current: &ContainerSample) -> Option<Self> {
+        let elapsed = current
+            .timestamp
+            .checked_duration_since(previous.timestamp)?
+            .as_secs_f64();
+        if elapsed == 0.0 {
+            return None;
+        }
+
+        // Counters go back to zero when a container restarts, treat that as no traffic
+        let rate = |prev: u64, curr: u64| curr.saturating_sub(prev) as f64 / elapsed;
+
+        Some(Self {
+            net_rx: rate(previous.net_rx, current.net_rx),
+            net_tx: rate(previous.net_tx, current.net_tx),
+            block_read: rate(previous.block_read, current.block_read),
+            block_write: rate(previous.block_write, current.block_write)
+        })
+    }
+
+    /// Whether two samples carry the same counters, as when a source repeats one the daemon didn't refresh yet.
+    fn unchanged(previous: &ContainerSample, current: &ContainerSample) -> bool {
+        (previous.net_rx, previous.net_tx, previous.block_read, previous.block_write)
+            == (current.net_rx, current.net_tx, current.block_read, current.block_write)
+    }
+}
+
+/// The values a metric took over the last few seconds, each with when it was sampled.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    values: VecDeque<(Instant, f64)>,
+    window: Duration
+}
+
+impl History {
+    /// Keeps the values of the last `seconds`, however often they come in.
+    pub fn new(seconds: usize) -> Self {
+        Self {
+            values: VecDeque::new(),
+            window: Duration::from_secs(seconds as u64)
+        }
+    }
+
+    /// Appends a value sampled `at`, evicting the ones that fell out of the window.
+    pub fn push(&mut self, at: Instant, value: f64) {
+        if self.window.is_zero() {
+            return;
+        }
+
+        self.values.push_back((at, value));
+        while self
+            .values
+            .front()
+            .is_some_and(|(t, _)| at.saturating_duration_since(*t) >= self.window)
+        {
+            self.values.pop_front();
+        }
+    }
+
+    /// The values from oldest to newest.
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = f64> + ExactSizeIterator + '_ { self.values.iter().map(|(_, v)| *v) }
+
+    /// The values from oldest to newest, with when they were sampled.
+    pub fn points(&self) -> impl DoubleEndedIterator<Item = (Instant, f64)> + ExactSizeIterator + '_ { self.values.iter().copied() }
+
+    /// When the newest value was sampled.
+    pub fn latest(&self) -> Option<Instant> { self.values.back().map(|(t, _)| *t) }
+
+    pub fn window(&self) -> Duration { self.window }
+
+    pub fn max(&self) -> f64 { self.values().fold(0.0, f64::max) }
+}
+
+/// A container's events over the same window as its histories, each with when it arrived.
+#[derive(Debug, Clone, Default)]
+pub struct Marks {
+    marks: VecDeque<(Instant, EventKind)>,
+    window: Duration
+}
+
+impl Marks {
+    pub fn new(seconds: usize) -> Self {
+        Self {
+            marks: VecDeque::new(),
+            window: Duration::from_secs(seconds as u64)
+        }
+    }
+
+    /// Forgets the events that fell out of the window as of `now`.
+    fn trim(&mut self, now: Instant) {
+        while self
+            .marks
+            .front()
+            .is_some_and(|(t, _)| now.saturating_duration_since(*t) >= self.window)
+        {
+            self.marks.pop_front();
+        }
+    }
+
+    /// Marks an event that happened `at`.
+    pub fn mark(&mut self, at: Instant, kind: EventKind) {
+        if !self.window.is_zero() {
+            self.marks.push_back((at, kind));
+        }
+    }
+
+    /// The events from oldest to newest, with when they happened.
+    pub fn events(&self) -> impl DoubleEndedIterator<Item = (Instant, &EventKind)> { self.marks.iter().map(|(t, k)| (*t, k)) }
+}
+
+/// The recent history of every charted metric of a container.
+#[derive(Debug, Clone, Default)]
+pub struct Histories {
+    pub cpu: History,
+    pub mem: History,
+    pub net_rx: History,
+    pub net_tx: History,
+    pub block_read: History,
+    pub block_write: History,
+    pub events: Marks
+}
+
+impl Histories {
+    /// Keeps the last `seconds` of every metric.
+    pub fn new(seconds: usize) -> Self {
+        Self {
+            cpu: History::new(seconds),
+            mem: History::new(seconds),
+            net_rx: History::new(seconds),
+            net_tx: History::new(seconds),
+            block_read: History::new(seconds),
+            block_write: History::new(seconds),
+            events: Marks::new(seconds)
+        }
+    }
+
+    fn push(&mut self, sample: &ContainerSample, rates: &Rates) {
+        let at = sample.timestamp;
+        self.cpu.push(at, sample.cpu_perc);
+        self.mem.push(at, sample.mem_usage as f64);
+        self.net_rx.push(at, rates.net_rx);
+        self.net_tx.push(at, rates.net_tx);
+        self.block_read.push(at, rates.block_read);
+        self.block_write.push(at, rates.block_write);
+        self.events.trim(at);
+    }
+}
+
+/// OOM kills and restarts seen during the session. Each signal a source offers is counted on its own,
+/// as they report the same incidents, and the highest count wins.
+#[derive(Debug, Clone, Default)]
+pub struct Incidents {
+    oom_events: u64,
+    /// Kills counted by the cgroup since the first sample, which it had counted so far.
+    oom_kills: u64,
+    first_oom_kills: Option<u64>,
+    /// Times the runtime was seen flagging the last exit as an OOM kill, after the flag it first showed.
+    oom_flags: u64,
+    oom_flagged: Option<bool>,
+    restart_events: u64,
+    /// Restarts counted by the runtime since the container was first described.
+    restart_count: u64,
+    first_restart_count: Option<u64>,
+    /// When each restart was seen, to tell a crash loop.
+    restarted: VecDeque<Instant>
+}
+
+impl Incidents {
+    pub fn ooms(&self) -> u64 { self.oom_events.max(self.oom_kills).max(self.oom_flags) }
+
+    pub fn restarts(&self) -> u64 { self.restart_events.max(self.restart_count) }
+
+    /// Whether the container restarted [`RESTART_LOOP_COUNT`] times within the last [`RESTART_LOOP_WINDOW`].
+    pub fn crash_looping(&self) -> bool {
+        self.restarted
+            .iter()
+            .filter(|at| at.elapsed() < RESTART_LOOP_WINDOW)
+            .count()
+            >= RESTART_LOOP_COUNT
+    }
+
+    fn sample(&mut self, sample: &ContainerSample) {
+        if let Some(kills) = sample.oom_kills {
+            let first = *self.first_oom_kills.get_or_insert(kills);
+            self.oom_kills = kills.saturating_sub(first);
+        }
+    }
+
+    fn describe(&mut self, metadata: &Metadata) {
+        // Only listings with details know the flag and the count
+        if metadata.started_at.is_some() {
+            let flagged = *self.oom_flagged.get_or_insert(metadata.oom_killed);
+            if metadata.oom_killed && !flagged {
+                self.oom_flags += 1;
+            }
+            self.oom_flagged = Some(metadata.oom_killed);
+
+            let first = *self.first_restart_count.get_or_insert(metadata.restarts);
+            self.counted(|i| i.restart_count = metadata.restarts.saturating_sub(first));
+        }
+    }
+
+    fn event(&mut self, kind: &EventKind) {
+        match kind {
+            EventKind::Oom => self.oom_events += 1,
+            EventKind::Restart => self.counted(|i| i.restart_events += 1),
+            _ => {}
+        }
+    }
+
+    /// Updates a restart count, noting when the restarts it adds happened.
+    fn counted(&mut self, update: impl FnOnce(&mut Self)) {
+        let before = self.restarts();
+        update(self);
+
+        for _ in before..self.restarts() {
+            if self.restarted.len() == RESTART_LOOP_COUNT {
+                self.restarted.pop_front();
+            }
+            self.restarted.push_back(Instant::now());
+        }
+    }
+}
+
+/// The values accepted by `--cpu-mode`.
+pub const CPU_MODES: [&str; 3] = ["core", "host", "quota"];
+
+/// What a container's CPU percentage is relative to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CpuMode {
+    /// A single core, like `docker stats`.
+    #[default]
+    Core,
+    /// Every core of the host.
+    Host,
+    /// The container's CPU limit, or every core of the host without one.
+    Quota
+}
+
+impl CpuMode {
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "core" => Some(Self::Core),
+            "host" => Some(Self::Host),
+            "quota" => Some(Self::Quota),
+            _ => None
+        }
+    }
+
+    /// How many CPUs make up 100% for the container.
+    pub fn cpus(&self, state: &ContainerState) -> f64 {
+        let host = || {
+            state
+                .sample
+                .host_cpus
+                .map_or_else(host_cpus, |cpus| cpus as usize) as f64
+        };
+        match self {
+            Self::Core => 1.0,
+            Self::Host => host(),
+            Self::Quota => state
+                .cpu_limit()
+                .filter(|cpus| *cpus > 0.0)
+                .unwrap_or_else(host)
+        }
+    }
+
+    /// The container's CPU usage as a percentage of what this mode counts as 100%.
+    pub fn perc(&self, state: &ContainerState) -> f64 { state.sample.cpu_perc / self.cpus(state) }
+
+    /// The summed CPU usage of several containers, against their summed limits in quota mode.
+    pub fn total<'a>(&self, states: impl IntoIterator<Item = &'a ContainerState>) -> f64 {
+        match self {
+            Self::Core | Self::Host => states.into_iter().map(|state| self.perc(state)).sum(),
+            Self::Quota => {
+                let (used, cpus) = states.into_iter().fold((0.0, 0.0), |(used, cpus), state| {
+                    (used + state.sample.cpu_perc, cpus + self.cpus(state))
+                });
+                if cpus > 0.0 {
+                    used / cpus
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+/// Everything tracked for a single container across samples.
+#[derive(Debug, Clone)]
+pub struct ContainerState {
+    pub sample: ContainerSample,
+    pub rates: Rates,
+    pub history: Histories,
+    /// Only sources talking to a runtime API fill this in.
+    pub metadata: Metadata,
+    /// When the container showed up under a new ID with the name of one seen before.
+    pub recreated: Option<Instant>,
+    /// When the source saw the container stop, its row lingers for a while after.
+    pub exited: Option<Instant>,
+    pub incidents: Incidents,
+    /// The sample the current rates were computed up to.
+    rated: ContainerSample
+}
+
+impl ContainerState {
+    /// Starts tracking a container, remembering the last `history` seconds of each metric.
+    pub fn new(sample: ContainerSample, history: usize) -> Self {
+        let mut history = Histories::new(history);
+        history.push(&sample, &Rates::default());
+        let mut incidents = Incidents::default();
+        incidents.sample(&sample);
+
+        Self {
+            sample: sample.clone(),
+            rates: Rates::default(),
+            history,
+            metadata: Metadata::default(),
+            recreated: None,
+            exited: None,
+            incidents,
+            rated: sample
+        }
+    }
+
+    pub fn id(&self) -> &str { &self.sample.id }
+
+    /// When the container was last known to be up: when it exited, or its last sample if it just went quiet.
+    pub fn last_seen(&self) -> Instant { self.exited.unwrap_or(self.sample.timestamp) }
+
+    /// Whether the runtime reports the container as stopped, which `--all` lists too.
+    pub fn is_stopped(&self) -> bool { engine::is_stopped(&self.metadata.state) }
+
+    /// Whether the container exited or stopped sending samples.
+    pub fn is_stale(&self) -> bool { self.exited.is_some() || self.sample.timestamp.elapsed() > STALE_AFTER }
+
+    /// Takes in a newer sample, keeping the previous rates while its counters repeat for less than [`RATE_INTERVAL`].
+    /// A sample from an exited container means it's back up.
+    pub fn update(&mut self, sample: ContainerSample) {
+        self.exited = None;
+        let elapsed = sample
+            .timestamp
+            .saturating_duration_since(self.rated.timestamp);
+        if !Rates::unchanged(&self.rated, &sample) || elapsed >= RATE_INTERVAL {
+            if let Some(rates) = Rates::between(&self.rated, &sample) {
+                self.rates = rates;
+                self.rated = sample.clone();
+            }
+        }
+        self.history.push(&sample, &self.rates);
+        self.incidents.sample(&sample);
+        self.sample = sample;
+    }
+
+    /// Takes in fresher metadata.
+    pub fn describe(&mut self, metadata: Metadata) {
+        self.incidents.describe(&metadata);
+        self.metadata = metadata;
+    }
+
+    /// Marks an event on the history, counting the OOM kills and restarts.
+    pub fn record(&mut self, kind: EventKind) {
+        self.incidents.event(&kind);
+        self.history.events.mark(Instant::now(), kind);
+    }
+
+    /// Whether the container has a memory limit of its own, `None` when the source can't tell.
+    pub fn mem_limited(&self) -> Option<bool> { self.sample.mem_limited.or(self.metadata.mem_limited) }
+
+    /// The container's CPU limit in CPUs, `None` without one or when the source can't tell.
+    pub fn cpu_limit(&self) -> Option<f64> { self.sample.cpu_limit.or(self.metadata.cpu_limit) }
+
+    /// Whether the container keeps restarting, by the count or as the runtime reports it.
+    pub fn is_crash_looping(&self) -> bool { self.incidents.crash_looping() || self.metadata.state == "restarting" }
+}
@@ -0,0 +1,81 @@
+use crate::error::{AppError, Result};
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen}
+};
+use std::{io, time::Duration};
+
+/// What a key press asks the interactive mode to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down,
+    Open,
+    Back,
+    /// Sort by the next key.
+    Sort,
+    /// Flip the sort direction.
+    Reverse,
+    Quit,
+    /// The terminal changed size and the frame should be drawn again.
+    Redraw
+}
+
+impl Action {
+    /// Maps a terminal event to an action, ignoring keys that have none.
+    pub fn from_event(event: Event) -> Option<Self> {
+        match event {
+            Event::Key(key) if key.kind != KeyEventKind::Release => Self::from_key(key),
+            Event::Resize(..) => Some(Self::Redraw),
+            _ => None
+        }
+    }
+
+    fn from_key(key: KeyEvent) -> Option<Self> {
+        // Raw mode swallows the SIGINT, so Ctrl+C has to be handled as a key
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            return (key.code == KeyCode::Char('c')).then_some(Self::Quit);
+        }
+
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => Some(Self::Up),
+            KeyCode::Down | KeyCode::Char('j') => Some(Self::Down),
+            KeyCode::Enter => Some(Self::Open),
+            KeyCode::Esc => Some(Self::Back),
+            KeyCode::Char('s') => Some(Self::Sort),
+            KeyCode::Char('r') => Some(Self::Reverse),
+            KeyCode::Char('q') => Some(Self::Quit),
+            _ => None
+        }
+    }
+}
+
+/// Waits up to `timeout` for a key press, `None` if nothing meaningful was pressed.
+pub fn next_action(timeout: Duration) -> Result<Option<Action>> {
+    if !event::poll(timeout).map_err(terminal_error)? {
+        return Ok(None);
+    }
+
+    Ok(Action::from_event(event::read().map_err(terminal_error)?))
+}
+
+/// Puts the terminal in raw mode on the alternate screen, restoring it when dropped.
+pub struct Terminal;
+
+impl Terminal {
+    pub fn enter() -> Result<Self> {
+        terminal::enable_raw_mode().map_err(terminal_error)?;
+        execute!(io::stdout(), EnterAlternateScreen).map_err(terminal_error)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn terminal_error(e: io::Error) -> AppError { AppError::TerminalError(e.to_string()) }
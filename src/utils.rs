@@ -1,22 +1,34 @@
 #![allow(dead_code)]
 
+use byte_unit::{Byte, UnitType};
 use clap::ArgMatches;
 use colored::{ColoredString, Colorize};
-use terminal_size::{terminal_size, Width};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use terminal_size::{terminal_size, Height, Width};
 
-/// Builds the command to run.
-pub fn build_command(matches: ArgMatches) -> Vec<String> {
+/// Builds the `stats` command arguments for the given containers.
+pub fn build_command(containers: Vec<String>) -> Vec<String> {
     let mut command = vec!["stats".to_string(), "--format".to_string(), "json".to_string()];
-
-    if let Some(containers) = matches.get_many::<String>("CONTAINER") {
-        command.extend(containers.cloned());
-    }
-
+    command.extend(containers);
     command
 }
 
+/// Whether an executable with the given name can be found in `PATH`.
+pub fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
 pub fn get_flag(args: &ArgMatches, id: &str) -> bool { args.get_one::<bool>(id).is_some_and(|x| *x) }
 
+/// Every value given for a repeatable argument.
+pub fn values<T: Clone + Send + Sync + 'static>(args: &ArgMatches, id: &str) -> Vec<T> {
+    args.get_many::<T>(id)
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect()
+}
+
 /// Gets the current terminal width.
 pub fn get_terminal_width() -> usize {
     if let Some((Width(w), _)) = terminal_size() {
@@ -26,6 +38,15 @@ pub fn get_terminal_width() -> usize {
     }
 }
 
+/// Gets the current terminal height.
+pub fn get_terminal_height() -> usize {
+    if let Some((_, Height(h))) = terminal_size() {
+        h.into()
+    } else {
+        24
+    }
+}
+
 /// Fills the size with the given char.
 pub fn filler(char: &str, max: usize, used: usize) -> String {
     if max == 0 || max <= used {
@@ -35,6 +56,18 @@ pub fn filler(char: &str, max: usize, used: usize) -> String {
     }
 }
 
+/// Shortens text to at most `max` characters, marking the cut with an ellipsis.
+pub fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+
+    match max {
+        0 => String::new(),
+        max => text.chars().take(max - 1).chain(['…']).collect()
+    }
+}
+
 /// Fills the size with the given char, but only on even numbers.
 pub fn fill_on_even(char: &str, size: usize, len: usize) -> String {
     if size == 0 || size <= len {
@@ -54,6 +87,69 @@ pub fn fill_on_even(char: &str, size: usize, len: usize) -> String {
     }
 }
 
+/// Formats a byte count the way the docker CLI does, e.g. `1.5MiB` or `3KB`.
+pub fn format_bytes(bytes: u64, unit: UnitType) -> String { format!("{:-#.2}", Byte::from_u64(bytes).get_appropriate_unit(unit)) }
+
+/// Formats a duration with its two largest units, e.g. `3d 4h`, `2h 13m` or `42s`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours, mins) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60);
+
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{mins}m {}s", secs % 60),
+        3600..86400 => format!("{hours}h {mins}m"),
+        _ => format!("{days}d {hours}h")
+    }
+}
+
+/// The current time in seconds since the Unix epoch.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// How many CPUs this machine has, for sources that don't report the host's.
+pub fn host_cpus() -> usize { std::thread::available_parallelism().map_or(1, |n| n.get()) }
+
+/// Parses an RFC 3339 timestamp like `2024-05-01T12:00:00.123456789Z` into seconds since the Unix epoch.
+/// Dates before the epoch, such as the zero time Docker reports for containers that never started, give `None`.
+pub fn parse_timestamp(text: &str) -> Option<u64> {
+    let (date, time) = text.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+
+    // The offset follows the seconds and their fraction
+    let split = time.find(['Z', 'z', '+', '-'])?;
+    let (clock, offset) = time.split_at(split);
+    let mut clock = clock.split(['.', ':']).map(|p| p.parse::<i64>().ok());
+    let (hour, min, sec) = (clock.next()??, clock.next()??, clock.next()??);
+
+    let offset = match offset.split_at(1) {
+        ("Z" | "z", _) => 0,
+        (sign, zone) => {
+            let (hours, mins) = zone.split_once(':')?;
+            let offset = hours.parse::<i64>().ok()? * 3600 + mins.parse::<i64>().ok()? * 60;
+            if sign == "-" {
+                -offset
+            } else {
+                offset
+            }
+        }
+    };
+
+    // Days since the epoch of a proleptic Gregorian date
+    let (y, m) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let era = y.div_euclid(400);
+    let day_of_era = y - era * 400;
+    let day_of_year = (153 * m + 2) / 5 + day - 1;
+    let days = era * 146097 + day_of_era * 365 + day_of_era / 4 - day_of_era / 100 + day_of_year - 719468;
+
+    u64::try_from(days * 86400 + hour * 3600 + min * 60 + sec - offset).ok()
+}
+
 /// Parses a percentage string into a usize.
 pub fn perc_to_float(perc: &str) -> f32 {
     if let Some(stripped) = perc.strip_suffix('%') {
@@ -63,7 +159,7 @@ pub fn perc_to_float(perc: &str) -> f32 {
     }
 }
 
-/// Converts a usize to a colored status bar.
+/// Converts a usize to a colored status bar, red as it nears `max`, the length standing for 100%.
 pub fn usize_to_status(perc: usize, max: usize) -> ColoredString {
     let fill = filler("█", perc, 0);
 
@@ -76,6 +172,23 @@ pub fn usize_to_status(perc: usize, max: usize) -> ColoredString {
     }
 }
 
+/// Renders the last `width` values as a sparkline scaled against `max`, padded on the left.
+pub fn sparkline(values: impl DoubleEndedIterator<Item = f64>, max: f64, width: usize) -> String {
+    const TICKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let mut line: Vec<char> = values
+        .rev()
+        .take(width)
+        .map(|value| {
+            let level = if max > 0.0 { (value / max * 7.0).round() } else { 0.0 };
+            TICKS[level.clamp(0.0, 7.0) as usize]
+        })
+        .collect();
+
+    line.resize(width, ' ');
+    line.iter().rev().collect()
+}
+
 /// Splits a value into two balanced parts.
 pub fn balanced_split(value: usize) -> Vec<usize> { vec![value / 2, value / 2 + value % 2] }
 
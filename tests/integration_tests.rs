@@ -1,4 +1,12 @@
-use docker_stats::{data::DockerStats, display::StatsDisplay, error::AppError, escape::EscapeSequenceCleaner, utils};
+use docker_stats::{
+    data::{self, ContainerSample, DockerStats},
+    display::{MemScale, Selection, StatsDisplay},
+    error::AppError,
+    escape::EscapeSequenceCleaner,
+    group::{self, GroupBy},
+    state::{ContainerState, CpuMode},
+    utils
+};
 use std::io::{Error as IoError, ErrorKind};
 
 #[cfg(test)]
@@ -17,6 +25,30 @@ mod error_tests {
         assert_eq!(format!("{error}"), "Docker daemon is not running. Please start Docker.");
     }
 
+    #[test]
+    fn display_api_error() {
+        let error = AppError::ApiError(404, "No such container: abc123".to_string());
+        assert_eq!(format!("{error}"), "Docker API error (404): No such container: abc123");
+    }
+
+    #[test]
+    fn display_socket_unavailable() {
+        let error = AppError::SocketUnavailable(
+            "/var/run/docker.sock".to_string(),
+            IoError::new(ErrorKind::ConnectionRefused, "connection refused")
+        );
+        assert_eq!(
+            format!("{error}"),
+            "Can't connect to the container runtime at /var/run/docker.sock: connection refused. Is the daemon running?"
+        );
+    }
+
+    #[test]
+    fn display_command_failed() {
+        let error = AppError::CommandFailed("no such container".to_string());
+        assert_eq!(format!("{error}"), "Command failed: no such container");
+    }
+
     #[test]
     fn display_json_parse_error() {
         let error = AppError::JsonParseError("invalid syntax".to_string());
@@ -29,6 +61,12 @@ mod error_tests {
         assert_eq!(format!("{error}"), "Terminal error: terminal size unknown");
     }
 
+    #[test]
+    fn display_usage() {
+        let error = AppError::Usage("--events needs the Docker or Podman API".to_string());
+        assert_eq!(format!("{error}"), "--events needs the Docker or Podman API");
+    }
+
     #[test]
     fn convert_io_error_not_found() {
         let io_error = IoError::new(ErrorKind::NotFound, "docker command not found");
@@ -67,6 +105,7 @@ mod error_tests {
 mod utils_tests {
     use super::*;
     use colored::Colorize;
+    use std::time::Duration;
 
     #[test]
     fn usize_to_status() {
@@ -118,6 +157,45 @@ mod utils_tests {
         assert_eq!(utils::perc_to_float("9237%"), 9237_f32);
     }
 
+    #[test]
+    fn sparkline() {
+        // Pitfalls
+        assert_eq!(utils::sparkline([].into_iter(), 10.0, 3), "   ");
+        assert_eq!(utils::sparkline([5.0].into_iter(), 0.0, 1), "▁");
+
+        // Common cases
+        assert_eq!(utils::sparkline([0.0, 5.0, 10.0].into_iter(), 10.0, 3), "▁▅█");
+        assert_eq!(utils::sparkline([0.0, 5.0, 10.0].into_iter(), 10.0, 5), "  ▁▅█");
+
+        // Only the most recent values fit, anything over the max is clamped
+        assert_eq!(utils::sparkline([0.0, 5.0, 20.0].into_iter(), 10.0, 2), "▅█");
+    }
+
+    #[test]
+    fn format_duration() {
+        assert_eq!(utils::format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(utils::format_duration(Duration::from_secs(130)), "2m 10s");
+        assert_eq!(utils::format_duration(Duration::from_secs(7980)), "2h 13m");
+        assert_eq!(utils::format_duration(Duration::from_secs(273600)), "3d 4h");
+    }
+
+    #[test]
+    fn parse_timestamp() {
+        assert_eq!(utils::parse_timestamp("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(utils::parse_timestamp("2024-05-01T12:30:15.123456789Z"), Some(1714566615));
+        assert_eq!(utils::parse_timestamp("2024-05-01T14:30:15+02:00"), Some(1714566615));
+        // Docker's zero time for containers that never started
+        assert_eq!(utils::parse_timestamp("0001-01-01T00:00:00Z"), None);
+        assert_eq!(utils::parse_timestamp("yesterday"), None);
+    }
+
+    #[test]
+    fn truncate() {
+        assert_eq!(utils::truncate("web", 5), "web");
+        assert_eq!(utils::truncate("web-frontend", 5), "web-…");
+        assert_eq!(utils::truncate("web", 0), "");
+    }
+
     #[test]
     fn balanced_split() {
         assert_eq!(utils::balanced_split(0), vec![0, 0]);
@@ -126,6 +204,12 @@ mod utils_tests {
         assert_eq!(utils::balanced_split(3), vec![1, 2]);
     }
 
+    #[test]
+    fn on_path() {
+        assert!(utils::on_path("sh"));
+        assert!(!utils::on_path("definitely-not-a-real-binary"));
+    }
+
     #[test]
     fn get_terminal_width() {
         assert!(utils::get_terminal_width() > 0);
@@ -135,17 +219,18 @@ mod utils_tests {
 #[cfg(test)]
 mod display_tests {
     use super::*;
+    use docker_stats::event::{Event, EventKind, EventLog};
+    use std::sync::{Arc, Mutex};
 
-    fn create_test_stats() -> DockerStats {
-        DockerStats {
-            block_io: "1.2kB / 0B".to_string(),
-            cpu_perc: "25.5%".to_string(),
-            id: "abc123".to_string(),
-            mem_perc: "50.0%".to_string(),
-            mem_usage: "512MB / 1GB".to_string(),
-            name: "test-container".to_string(),
-            net_io: "10kB / 5kB".to_string()
-        }
+    fn create_test_stats() -> ContainerSample {
+        ContainerSample::new(
+            "abc123",
+            "test-container",
+            25.5,
+            (512_000_000, 1_024_000_000),
+            (10_000, 5_000),
+            (1_200, 0)
+        )
     }
 
     #[test]
@@ -175,11 +260,300 @@ mod display_tests {
     }
 
     #[test]
-    fn docker_stats_creation() {
+    fn cpu_modes() {
+        let mut sample = create_test_stats();
+        sample.cpu_perc = 200.0;
+        sample.host_cpus = Some(8);
+        let mut state = ContainerState::new(sample, 60);
+
+        assert_eq!(CpuMode::parse("host"), Some(CpuMode::Host));
+        assert_eq!(CpuMode::parse("cores"), None);
+        assert_eq!(CpuMode::Core.perc(&state), 200.0);
+        assert_eq!(CpuMode::Host.perc(&state), 25.0);
+
+        // Without a limit, the quota is the whole host
+        assert_eq!(CpuMode::Quota.perc(&state), 25.0);
+        state.metadata.cpu_limit = Some(4.0);
+        assert_eq!(CpuMode::Quota.perc(&state), 50.0);
+    }
+
+    #[test]
+    fn mem_scales() {
+        assert_eq!(MemScale::parse("global"), Some(MemScale::Global));
+        assert_eq!(MemScale::parse("local"), Some(MemScale::Local));
+        assert_eq!(MemScale::parse("host"), None);
+    }
+
+    #[test]
+    fn stats_display_resize() {
+        let mut display = StatsDisplay::new(80, false, false);
+        assert!(display.resize(80, 24));
+        assert!(!display.resize(80, 24));
+        assert!(display.resize(40, 24));
+    }
+
+    #[test]
+    fn print_stats_at_any_width() {
+        let mut described = ContainerState::new(
+            ContainerSample {
+                id: "def456".to_string(),
+                ..create_test_stats()
+            },
+            60
+        );
+        described.metadata = data::Metadata {
+            image: "nginx:1.27".to_string(),
+            state: "running".to_string(),
+            started_at: Some(utils::unix_now() - 90),
+            health: Some("healthy".to_string()),
+            ports: vec![data::Port {
+                ip: None,
+                public: 8080,
+                private: 80,
+                protocol: "tcp".to_string()
+            }],
+            ..Default::default()
+        };
+        described.recreated = Some(std::time::Instant::now());
+        described.metadata.mem_limited = Some(false);
+        described.sample.mem_breakdown = Some(data::MemoryBreakdown {
+            anon: 300_000_000,
+            file: 200_000_000,
+            kernel: Some(10_000_000),
+            swap: None
+        });
+        let mut exited = ContainerState::new(
+            ContainerSample {
+                id: "fed789".to_string(),
+                ..create_test_stats()
+            },
+            60
+        );
+        exited.exited = Some(std::time::Instant::now());
+        exited.metadata.state = "restarting".to_string();
+        let mut stopped = ContainerState::new(
+            ContainerSample {
+                id: "cba987".to_string(),
+                ..create_test_stats()
+            },
+            60
+        );
+        stopped.metadata.state = "exited".to_string();
+        stopped.metadata.exit_code = 137;
+        stopped.metadata.finished_at = Some(utils::unix_now() - 300);
+        stopped.metadata.oom_killed = true;
+        stopped.describe(stopped.metadata.clone());
+        stopped.record(EventKind::Oom);
+        let states = [ContainerState::new(create_test_stats(), 60), described, exited, stopped];
+
+        let log = Arc::new(Mutex::new(EventLog::default()));
+        log.lock().unwrap().push(Event {
+            at: utils::unix_now(),
+            id: "fed789".to_string(),
+            name: "test-container".to_string(),
+            kind: EventKind::Die(137)
+        });
+
+        for width in 0..120 {
+            for (compact, full, cpu_mode, mem_scale) in [
+                (false, false, CpuMode::Core, MemScale::Local),
+                (true, true, CpuMode::Quota, MemScale::Global)
+            ] {
+                let mut display = StatsDisplay::new(width, compact, full)
+                    .with_cpu_mode(cpu_mode)
+                    .with_mem_scale(mem_scale)
+                    .with_summary()
+                    .with_events(log.clone());
+                display.resize(width, 40);
+                display.print_stats(&states);
+            }
+
+            let mut display = StatsDisplay::new(width, false, false).with_chart("test-container");
+            display.resize(width, 40);
+            display.print_stats(&states);
+        }
+    }
+
+    #[test]
+    fn print_stats_pages_through_containers() {
+        let states: Vec<ContainerState> = (0..10)
+            .map(|i| {
+                ContainerState::new(
+                    ContainerSample {
+                        id: format!("abc{i}"),
+                        ..create_test_stats()
+                    },
+                    60
+                )
+            })
+            .collect();
+
+        let mut display = StatsDisplay::new(80, false, true);
+        display.resize(80, 20);
+        display.print_stats(&states);
+        let ids = |page: Vec<Selection>| -> Vec<String> {
+            page.into_iter()
+                .map(|entry| match entry {
+                    Selection::Container(id) => id,
+                    Selection::Group(_) => panic!("Expected only containers")
+                })
+                .collect()
+        };
+
+        // Every container shows up on exactly one page
+        let first = ids(display.page(&states));
+        let mut seen = first.clone();
+        for _ in 0..10 {
+            display.next_page(&states);
+            display.print_stats(&states);
+            let page = ids(display.page(&states));
+            if page == first {
+                break;
+            }
+            seen.extend(page);
+        }
+        let expected: Vec<String> = (0..10).map(|i| format!("abc{i}")).collect();
+        assert_eq!(seen, expected);
+        assert!(first.len() < 10);
+
+        // Flipping past the last page wraps around to the first
+        assert_eq!(ids(display.page(&states)), first);
+    }
+
+    #[test]
+    fn interactive_selection_and_detail() {
+        let states: Vec<ContainerState> = (0..10)
+            .map(|i| {
+                ContainerState::new(
+                    ContainerSample {
+                        id: format!("abc{i}"),
+                        ..create_test_stats()
+                    },
+                    60
+                )
+            })
+            .collect();
+
+        let selected = |i: usize| Selection::Container(format!("abc{i}"));
+        let mut display = StatsDisplay::new(80, false, true).interactive();
+        display.resize(80, 30);
+        display.print_stats(&states);
+        assert_eq!(display.selected(), Some(&selected(0)));
+
+        // Moving past either end stays on the first or last container
+        display.select(&states, -3);
+        assert_eq!(display.selected(), Some(&selected(0)));
+        display.select(&states, 20);
+        assert_eq!(display.selected(), Some(&selected(9)));
+        display.print_stats(&states);
+        assert!(display.page(&states).contains(&selected(9)));
+
+        display.open();
+        display.print_stats(&states);
+        assert!(display.detail());
+
+        // The detail pane closes when its container goes away
+        display.print_stats(&states[..5]);
+        assert!(!display.detail());
+        assert_eq!(display.selected(), Some(&selected(0)));
+
+        display.open();
+        display.close();
+        assert!(!display.detail());
+        display.print_stats(&[]);
+        assert_eq!(display.selected(), None);
+    }
+
+    #[test]
+    fn grouped_sections_fold() {
+        let states: Vec<ContainerState> = (0..6)
+            .map(|i| {
+                let mut state = ContainerState::new(
+                    ContainerSample {
+                        id: format!("abc{i}"),
+                        ..create_test_stats()
+                    },
+                    60
+                );
+                if i < 4 {
+                    let labels = [
+                        (group::COMPOSE_PROJECT, format!("shop{}", i % 2)),
+                        (group::COMPOSE_SERVICE, "api".to_string())
+                    ];
+                    state.metadata.labels = labels
+                        .into_iter()
+                        .map(|(k, v)| (k.to_string(), v))
+                        .collect();
+                }
+                state
+            })
+            .collect();
+
+        let shop0 = Selection::Group(Some("shop0".to_string()));
+        let container = |i: usize| Selection::Container(format!("abc{i}"));
+        let mut display = StatsDisplay::new(80, true, false)
+            .interactive()
+            .with_group_by(GroupBy::Compose);
+        display.resize(80, 12);
+        display.print_stats(&states);
+        assert_eq!(display.page(&states)[..2], [shop0.clone(), container(0)]);
+
+        // Headers sum up their containers
+        let groups = group::group(&GroupBy::Compose, &states);
+        for group in &groups {
+            let totals = group.totals(CpuMode::Core);
+            let cpu: f64 = group.containers.iter().map(|s| s.sample.cpu_perc).sum();
+            let mem: u64 = group.containers.iter().map(|s| s.sample.mem_usage).sum();
+            assert_eq!((totals.cpu_perc, totals.mem_usage), (cpu, mem));
+        }
+
+        // Folding the first section hides its containers but keeps its header
+        assert_eq!(display.selected(), Some(&shop0));
+        display.open();
+        display.print_stats(&states);
+        let page = display.page(&states);
+        assert_eq!(page[..2], [shop0.clone(), Selection::Group(Some("shop1".to_string()))]);
+        assert!(!page.contains(&container(0)) && !page.contains(&container(2)));
+
+        // Scrolling down skips the folded containers, and keeps the cursor on the page
+        let mut visited = vec![shop0.clone()];
+        for _ in 0..10 {
+            display.select(&states, 1);
+            display.print_stats(&states);
+            let selected = display.selected().cloned().unwrap();
+            assert!(display.page(&states).contains(&selected));
+            if visited.last() == Some(&selected) {
+                break;
+            }
+            visited.push(selected);
+        }
+        assert_eq!(
+            visited,
+            [
+                shop0.clone(),
+                Selection::Group(Some("shop1".to_string())),
+                container(1),
+                container(3),
+                Selection::Group(None),
+                container(4),
+                container(5)
+            ]
+        );
+        assert_ne!(display.page(&states)[0], shop0);
+
+        // Unfolding brings them back
+        display.select(&states, -10);
+        display.open();
+        display.print_stats(&states);
+        assert!(display.page(&states).contains(&container(0)));
+    }
+
+    #[test]
+    fn container_sample_creation() {
         let stats = create_test_stats();
         assert_eq!(stats.name, "test-container");
-        assert_eq!(stats.cpu_perc, "25.5%");
-        assert_eq!(stats.mem_perc, "50.0%");
+        assert_eq!(stats.cpu_perc, 25.5);
+        assert_eq!(stats.mem_perc, 50.0);
     }
 }
 
@@ -200,11 +574,11 @@ mod data_tests {
         }"#;
 
         let stats: DockerStats = serde_json::from_str(json).unwrap();
-        assert_eq!(stats.cpu_perc, "0.50%");
-        assert_eq!(stats.mem_perc, "2.34%");
+        assert_eq!(stats.cpu_perc, 0.5);
+        assert_eq!(stats.mem_perc, 2.34);
         assert_eq!(stats.name, "test-container");
-        assert_eq!(stats.block_io, "1.2kB / 0B");
-        assert_eq!(stats.net_io, "10kB / 5kB");
+        assert_eq!(stats.block_io, (1_200, 0));
+        assert_eq!(stats.net_io, (10_000, 5_000));
     }
 
     #[test]
@@ -251,8 +625,87 @@ mod data_tests {
         }"#;
 
         let stats: DockerStats = serde_json::from_str(json).unwrap();
-        assert_eq!(stats.cpu_perc, "150.50%");
-        assert_eq!(stats.mem_perc, "95.67%");
+        assert_eq!(stats.cpu_perc, 150.5);
+        assert_eq!(stats.mem_perc, 95.67);
+        assert_eq!(stats.net_io, (1_000_000_000_000, 500_000_000_000));
+    }
+
+    #[test]
+    fn deserialize_into_sample() {
+        let json = r#"{
+            "BlockIO": "0B / 4.1kB",
+            "CPUPerc": "12.50%",
+            "ID": "abc123",
+            "MemPerc": "15.75%",
+            "MemUsage": "1.5GiB / 7.6GiB",
+            "Name": "api",
+            "NetIO": "3kB / 1kB"
+        }"#;
+
+        let sample: ContainerSample = serde_json::from_str(json).unwrap();
+        assert_eq!(sample.cpu_perc, 12.5);
+        assert_eq!(sample.mem_perc, 15.75);
+        assert_eq!(sample.mem_usage, 1_610_612_736);
+        assert_eq!((sample.net_rx, sample.net_tx), (3_000, 1_000));
+        assert_eq!((sample.block_read, sample.block_write), (0, 4_100));
+    }
+
+    #[test]
+    fn reject_malformed_values() {
+        let json = r#"{
+            "BlockIO": "0B / 0B",
+            "CPUPerc": "twelve",
+            "ID": "abc123",
+            "MemPerc": "0.00%",
+            "MemUsage": "0B / 0B",
+            "Name": "broken",
+            "NetIO": "0B / 0B"
+        }"#;
+
+        let error = serde_json::from_str::<ContainerSample>(json).unwrap_err();
+        assert!(error.to_string().contains("invalid percentage"));
+    }
+
+    #[test]
+    fn parse_values() {
+        assert_eq!(data::parse_percent("0.50%"), Ok(0.5));
+        assert_eq!(data::parse_percent("--"), Ok(0.0));
+        assert!(data::parse_percent("10").is_err());
+
+        assert_eq!(data::parse_byte_pair("1KiB / 2MB"), Ok((1_024, 2_000_000)));
+        assert_eq!(data::parse_byte_pair("-- / --"), Ok((0, 0)));
+        assert!(data::parse_byte_pair("1KiB").is_err());
+        assert!(data::parse_byte_pair("lots / 2MB").is_err());
+    }
+
+    #[test]
+    fn memory_breakdown() {
+        let stat = |pairs: &[(&str, u64)]| pairs.iter().map(|(k, v)| (k.to_string(), *v)).collect();
+
+        // v2, with the kernel memory counted piece by piece on older kernels
+        let v2 = data::MemoryBreakdown::from_stat(&stat(&[("anon", 300), ("file", 200), ("slab", 20), ("kernel_stack", 10)]));
+        assert_eq!(
+            v2,
+            Some(data::MemoryBreakdown {
+                anon: 300,
+                file: 200,
+                kernel: Some(30),
+                swap: None
+            })
+        );
+        let bare = data::MemoryBreakdown::from_stat(&stat(&[("anon", 300)])).unwrap();
+        assert_eq!(bare.kernel, None);
+
+        // v1 counts the whole hierarchy under `total_`
+        let v1 = data::MemoryBreakdown::from_stat(&stat(&[("rss", 1), ("total_rss", 300), ("total_cache", 200), ("total_swap", 50)])).unwrap();
+        assert_eq!((v1.anon, v1.file, v1.kernel, v1.swap), (300, 200, None, Some(50)));
+        assert_eq!(v1.total(), 550);
+
+        // Without swap accounting, swap isn't known rather than zero
+        let unaccounted = data::MemoryBreakdown::from_stat(&stat(&[("total_rss", 300)])).unwrap();
+        assert_eq!(unaccounted.swap, None);
+
+        assert_eq!(data::MemoryBreakdown::from_stat(&stat(&[("pgfault", 7)])), None);
     }
 }
 
@@ -283,3 +736,1426 @@ mod escape_tests {
         assert!(!EscapeSequenceCleaner::is_screen_clear_event("\u{1b}[H"));
     }
 }
+
+#[cfg(test)]
+mod engine_tests {
+    use super::*;
+    use docker_stats::engine::{EngineClient, HostConfig, StatsResponse};
+    use std::{
+        io::{BufRead, BufReader, Write},
+        os::unix::net::UnixListener,
+        path::PathBuf,
+        thread
+    };
+
+    /// Serves canned HTTP responses on a temporary Unix socket, keyed by request path.
+    pub fn fake_daemon(name: &str, routes: Vec<(&'static str, String)>) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("ds-{name}-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let mut reader = BufReader::new(&stream);
+                let mut request = String::new();
+                reader.read_line(&mut request).unwrap();
+
+                // Drain the request headers
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 0 && !header.trim().is_empty() {
+                    header.clear();
+                }
+
+                let target = request.split_whitespace().nth(1).unwrap_or_default();
+                let response = routes
+                    .iter()
+                    .find(|(route, _)| *route == target)
+                    .map(|(_, r)| r.clone())
+                    .unwrap_or_else(|| {
+                        let body = r#"{"message":"page not found"}"#;
+                        format!("HTTP/1.1 404 Not Found\r\nContent-Length: {}\r\n\r\n{body}", body.len())
+                    });
+
+                let _ = (&stream).write_all(response.as_bytes());
+            }
+        });
+
+        path
+    }
+
+    pub fn json_response(body: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+    }
+
+    pub fn chunked_response(chunks: &[&str]) -> String {
+        let mut response = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\n\r\n".to_string();
+        for chunk in chunks {
+            response.push_str(&format!("{:x}\r\n{chunk}\r\n", chunk.len()));
+        }
+        response + "0\r\n\r\n"
+    }
+
+    pub const STATS: &str = r#"{"id":"abc123","name":"/web","cpu_stats":{"cpu_usage":{"total_usage":300},"system_cpu_usage":2000,"online_cpus":2},"precpu_stats":{"cpu_usage":{"total_usage":100},"system_cpu_usage":1000},"memory_stats":{"usage":3145728,"limit":8388608,"stats":{"inactive_file":1048576}},"networks":{"eth0":{"rx_bytes":1000,"tx_bytes":500},"eth1":{"rx_bytes":1000,"tx_bytes":0}},"blkio_stats":{"io_service_bytes_recursive":[{"op":"Read","value":2000},{"op":"Write","value":3000},{"op":"Total","value":5000}]}}"#;
+
+    #[test]
+    fn list_containers() {
+        let body = r#"[{"Id":"abc123","Names":["/web"],"Image":"nginx","State":"running","Status":"Up 2 minutes"}]"#;
+        let socket = fake_daemon("list", vec![("/containers/json", json_response(body))]);
+
+        let containers = EngineClient::new(socket).list_containers(false).unwrap();
+        assert_eq!(containers.len(), 1);
+        assert_eq!(containers[0].name(), "web");
+        assert!(containers[0].matches("web"));
+        assert!(containers[0].matches("abc"));
+        assert!(!containers[0].matches("api"));
+    }
+
+    #[test]
+    fn container_metadata() {
+        let list = r#"[{"Id":"abc123","Names":["/web"],"Image":"nginx:1.27","State":"running","Ports":[
+            {"IP":"0.0.0.0","PrivatePort":80,"PublicPort":8080,"Type":"tcp"},
+            {"IP":"::","PrivatePort":80,"PublicPort":8080,"Type":"tcp"},
+            {"IP":"127.0.0.1","PrivatePort":9090,"PublicPort":9090,"Type":"tcp"},
+            {"PrivatePort":443,"Type":"tcp"}
+        ]}]"#;
+        let details = r#"{"RestartCount":2,"State":{"Status":"running","StartedAt":"2024-05-01T12:30:15.5Z","Health":{"Status":"healthy"}},"HostConfig":{"Memory":0,"NanoCpus":1500000000}}"#;
+        let socket = fake_daemon(
+            "inspect",
+            vec![
+                ("/containers/json", json_response(list)),
+                ("/containers/abc123/json", json_response(details)),
+            ]
+        );
+
+        let client = EngineClient::new(socket);
+        let summary = &client.list_containers(false).unwrap()[0];
+        let mut metadata = data::Metadata::from(summary);
+        client
+            .inspect_container("abc123")
+            .unwrap()
+            .fill(&mut metadata);
+
+        assert_eq!(metadata.image, "nginx:1.27");
+        assert_eq!(metadata.state, "running");
+        assert_eq!(metadata.started_at, Some(1714566615));
+        assert_eq!(metadata.health.as_deref(), Some("healthy"));
+        assert_eq!(metadata.restarts, 2);
+        assert_eq!(metadata.mem_limited, Some(false));
+        assert_eq!(metadata.cpu_limit, Some(1.5));
+
+        // Published ports only, bound to every interface once
+        let ports: Vec<String> = metadata.ports.iter().map(|p| p.to_string()).collect();
+        assert_eq!(ports, ["8080→80/tcp", "127.0.0.1:9090→9090/tcp"]);
+    }
+
+    #[test]
+    fn cpu_limits() {
+        let quota = HostConfig {
+            cpu_quota: 50_000,
+            ..HostConfig::default()
+        };
+        assert_eq!(quota.cpu_limit(), Some(0.5));
+        assert_eq!(HostConfig::default().cpu_limit(), None);
+    }
+
+    #[test]
+    fn stream_stats_across_chunks() {
+        // Objects split at arbitrary chunk boundaries must still be decoded
+        let (head, tail) = STATS.split_at(40);
+        let response = chunked_response(&[head, tail, "\n", STATS, "\n"]);
+        let socket = fake_daemon("stream", vec![("/containers/abc123/stats?stream=true", response)]);
+
+        let samples: Vec<StatsResponse> = EngineClient::new(socket)
+            .stream_stats("abc123")
+            .unwrap()
+            .map(|s| s.unwrap())
+            .collect();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[1].cpu_percent(), 40.0);
+        assert_eq!(samples[1].memory_usage(), 2097152);
+        assert_eq!(samples[1].network_io(), (2000, 500));
+        assert_eq!(samples[1].block_io(), (2000, 3000));
+    }
+
+    #[test]
+    fn api_error_status() {
+        let socket = fake_daemon("error", vec![]);
+
+        match EngineClient::new(socket).stream_stats("missing") {
+            Err(AppError::ApiError(404, message)) => assert_eq!(message, "page not found"),
+            _ => panic!("Expected ApiError")
+        }
+    }
+
+    #[test]
+    fn missing_socket() {
+        match EngineClient::new("/nonexistent/docker.sock").list_containers(false) {
+            Err(error @ AppError::SocketUnavailable(..)) => {
+                assert!(error
+                    .to_string()
+                    .starts_with("Can't connect to the container runtime at /nonexistent/docker.sock: "));
+            }
+            _ => panic!("Expected SocketUnavailable error")
+        }
+    }
+
+    #[test]
+    fn convert_to_sample() {
+        let stats: ContainerSample = serde_json::from_str::<StatsResponse>(STATS).unwrap().into();
+        assert_eq!(stats.name, "web");
+        assert_eq!(stats.cpu_perc, 40.0);
+        assert_eq!(stats.mem_perc, 25.0);
+        assert_eq!((stats.mem_usage, stats.mem_limit), (2_097_152, 8_388_608));
+        assert_eq!((stats.net_rx, stats.net_tx), (2_000, 500));
+        assert_eq!((stats.block_read, stats.block_write), (2_000, 3_000));
+    }
+}
+
+#[cfg(test)]
+mod source_tests {
+    use super::{
+        engine_tests::{chunked_response, fake_daemon, json_response, STATS},
+        *
+    };
+    use docker_stats::{
+        engine::EngineClient,
+        filter::{Filter, Label},
+        source::{self, EngineSource, Sink, StatsSource}
+    };
+    use std::{
+        collections::HashSet,
+        io::{BufRead, BufReader, Write},
+        os::unix::net::UnixListener,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc, Arc, Mutex
+        },
+        thread,
+        time::Duration
+    };
+
+    fn stats(id: &str, cpu: f64) -> ContainerSample { ContainerSample::new(id, &format!("container-{id}"), cpu, (0, 0), (0, 0), (0, 0)) }
+
+    #[test]
+    fn sink_publishes_by_id() {
+        static RUNNING: AtomicBool = AtomicBool::new(true);
+        let containers = Arc::new(Mutex::new(Vec::new()));
+        let (heartbeat_sender, heartbeat_receiver) = mpsc::channel();
+        let sink = Sink::new(containers.clone(), heartbeat_sender, &RUNNING, 60);
+
+        sink.publish(stats("a", 1.0));
+        sink.publish(stats("b", 2.0));
+        sink.publish(stats("a", 3.0));
+
+        let guard = containers.lock().unwrap();
+        assert_eq!(guard.len(), 2);
+        assert_eq!(guard[0].sample.cpu_perc, 3.0);
+        assert_eq!(heartbeat_receiver.try_iter().count(), 3);
+        drop(guard);
+
+        // Stopped containers are only marked, the display drops them after a grace period
+        sink.remove("a");
+        assert!(containers.lock().unwrap()[0].exited.is_some());
+        assert!(containers.lock().unwrap()[1].exited.is_none());
+        sink.retain(&HashSet::new());
+        assert!(containers.lock().unwrap().iter().all(|c| c.is_stale()));
+
+        // Until they come back
+        sink.publish(stats("a", 1.0));
+        assert!(containers.lock().unwrap()[0].exited.is_none());
+    }
+
+    #[test]
+    fn sink_detects_recreated_containers() {
+        static RUNNING: AtomicBool = AtomicBool::new(true);
+        let containers = Arc::new(Mutex::new(Vec::new()));
+        let (heartbeat_sender, _heartbeat_receiver) = mpsc::channel();
+        let sink = Sink::new(containers.clone(), heartbeat_sender, &RUNNING, 60);
+        let named = |id: &str, name: &str| ContainerSample::new(id, name, 1.0, (0, 0), (0, 0), (0, 0));
+
+        // A rename keeps the row and its history
+        sink.publish(named("a", "web"));
+        sink.publish(named("a", "web-renamed"));
+        {
+            let guard = containers.lock().unwrap();
+            assert_eq!(guard.len(), 1);
+            assert_eq!(guard[0].sample.name, "web-renamed");
+            assert_eq!(guard[0].history.cpu.values().len(), 2);
+            assert!(guard[0].recreated.is_none());
+        }
+
+        // A new ID under the same name replaces the row with a fresh one
+        sink.publish(named("b", "web-renamed"));
+        {
+            let guard = containers.lock().unwrap();
+            assert_eq!(guard.len(), 1);
+            assert_eq!(guard[0].id(), "b");
+            assert_eq!(guard[0].history.cpu.values().len(), 1);
+            assert!(guard[0].recreated.is_some());
+        }
+
+        // Also when the old container was gone before the new one showed up
+        sink.remove("b");
+        sink.publish(named("c", "web-renamed"));
+        sink.publish(named("d", "db"));
+        let guard = containers.lock().unwrap();
+        assert!(guard[0].recreated.is_some());
+        assert!(guard[1].recreated.is_none());
+    }
+
+    #[test]
+    fn select_unknown_source() {
+        assert!(source::select("containerd", Filter::default()).is_err());
+        assert_eq!(
+            source::select("docker-cli", Filter::default())
+                .unwrap()
+                .name(),
+            "docker stats"
+        );
+        assert_eq!(source::select("podman", Filter::default()).unwrap().name(), "Podman API");
+        assert_eq!(
+            source::select("podman-cli", Filter::default())
+                .unwrap()
+                .name(),
+            "podman stats"
+        );
+    }
+
+    #[test]
+    fn select_metadata_filters_need_an_api() {
+        let filter = Filter {
+            labels: vec![Label::parse("tier=web").unwrap()],
+            ..Filter::default()
+        };
+
+        let check = |kind: &str, filter: &Filter| {
+            let source = source::select(kind, filter.clone()).unwrap();
+            source::require_metadata(source.as_ref(), &filter.metadata_flags())
+        };
+
+        assert!(check("docker", &filter).is_ok());
+        assert!(matches!(check("docker-cli", &filter), Err(AppError::Usage(_))));
+        assert!(matches!(check("cgroup", &filter), Err(AppError::Usage(_))));
+
+        // Stopped containers only show up in the API listing
+        let filter = Filter {
+            all: true,
+            ..Filter::default()
+        };
+        assert!(check("podman", &filter).is_ok());
+        assert!(matches!(check("podman-cli", &filter), Err(AppError::Usage(_))));
+    }
+
+    #[test]
+    fn require_metadata_names_the_flags() {
+        let api = source::select("docker", Filter::default()).unwrap();
+        let cli = source::select("docker-cli", Filter::default()).unwrap();
+
+        assert!(source::require_metadata(api.as_ref(), &["--group-by", "--events"]).is_ok());
+        assert!(source::require_metadata(cli.as_ref(), &[]).is_ok());
+        match source::require_metadata(cli.as_ref(), &["--group-by"]) {
+            Err(error @ AppError::Usage(_)) => assert_eq!(
+                error.to_string(),
+                "--group-by needs the Docker or Podman API, which the docker stats source can't use"
+            ),
+            _ => panic!("Expected Usage error")
+        }
+        match source::require_metadata(cli.as_ref(), &["--label", "--all", "--events"]) {
+            Err(error) => assert!(error
+                .to_string()
+                .starts_with("--label, --all and --events need ")),
+            Ok(()) => panic!("Expected Usage error")
+        }
+    }
+
+    #[test]
+    fn engine_source_lists_stopped_containers() {
+        static RUNNING: AtomicBool = AtomicBool::new(true);
+        let list = r#"[{"Id":"def456","Names":["/db"],"State":"exited"}]"#;
+        let details = r#"{"State":{"Status":"exited","ExitCode":137,"FinishedAt":"2024-05-01T12:30:15Z"}}"#;
+        let socket = fake_daemon(
+            "stopped",
+            vec![
+                ("/containers/json?all=true", json_response(list)),
+                ("/containers/def456/json", json_response(details)),
+            ]
+        );
+
+        let containers = Arc::new(Mutex::new(Vec::new()));
+        let (heartbeat_sender, _heartbeat_receiver) = mpsc::channel();
+        let sink = Sink::new(containers.clone(), heartbeat_sender, &RUNNING, 60);
+        let filter = Filter {
+            all: true,
+            ..Filter::default()
+        };
+        let mut source = EngineSource::new("Docker Engine API", EngineClient::new(socket), filter);
+        let handle = thread::spawn(move || source.run(&sink));
+
+        thread::sleep(Duration::from_millis(300));
+        RUNNING.store(false, Ordering::SeqCst);
+        assert!(handle.join().unwrap().is_ok());
+
+        // Listed with empty stats, as nothing's there to stream
+        let guard = containers.lock().unwrap();
+        assert_eq!(guard.len(), 1);
+        assert!(guard[0].is_stopped());
+        assert_eq!(guard[0].sample.cpu_perc, 0.0);
+        assert_eq!(guard[0].metadata.exit_code, 137);
+        assert_eq!(guard[0].metadata.finished_at, Some(1714566615));
+    }
+
+    #[test]
+    fn engine_source_survives_failed_listings() {
+        static RUNNING: AtomicBool = AtomicBool::new(true);
+        let list = r#"[{"Id":"def456","Names":["/db"],"State":"exited"}]"#;
+
+        // Only the first listing goes through, as if the daemon restarted right after
+        let path = std::env::temp_dir().join(format!("ds-flaky-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let first = json_response(list);
+        thread::spawn(move || {
+            let mut listings = 0;
+            for stream in listener.incoming().flatten() {
+                let mut reader = BufReader::new(&stream);
+                let mut request = String::new();
+                reader.read_line(&mut request).unwrap();
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 0 && !header.trim().is_empty() {
+                    header.clear();
+                }
+
+                let listing = request.contains("/containers/json?");
+                listings += usize::from(listing);
+                let response = match (listing, listings) {
+                    (true, 1) => first.clone(),
+                    _ => "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\n\r\n".to_string()
+                };
+                let _ = (&stream).write_all(response.as_bytes());
+            }
+        });
+
+        let containers = Arc::new(Mutex::new(Vec::new()));
+        let (heartbeat_sender, _heartbeat_receiver) = mpsc::channel();
+        let sink = Sink::new(containers.clone(), heartbeat_sender, &RUNNING, 60);
+        let filter = Filter {
+            all: true,
+            ..Filter::default()
+        };
+        let mut source = EngineSource::new("Docker Engine API", EngineClient::new(path), filter);
+        let handle = thread::spawn(move || source.run(&sink));
+
+        // Long enough for a second listing to fail
+        thread::sleep(Duration::from_millis(2_300));
+        assert!(!handle.is_finished());
+        RUNNING.store(false, Ordering::SeqCst);
+        assert!(handle.join().unwrap().is_ok());
+        assert_eq!(containers.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn engine_source_fails_without_a_first_listing() {
+        static RUNNING: AtomicBool = AtomicBool::new(true);
+        let socket = fake_daemon("unlisted", vec![]);
+
+        let (heartbeat_sender, _heartbeat_receiver) = mpsc::channel();
+        let sink = Sink::new(Arc::new(Mutex::new(Vec::new())), heartbeat_sender, &RUNNING, 60);
+        let mut source = EngineSource::new("Docker Engine API", EngineClient::new(socket), Filter::default());
+        assert!(source.run(&sink).is_err());
+    }
+
+    #[test]
+    fn parse_podman_stats() {
+        let output = br#"[
+            {
+                "id": "e4c5f1a2b3d4",
+                "name": "web",
+                "cpu_time": "1.2s",
+                "cpu_percent": "1.50%",
+                "avg_cpu": "0.90%",
+                "mem_usage": "12.3MB / 8.2GB",
+                "mem_percent": "0.15%",
+                "net_io": "1.2kB / 648B",
+                "block_io": "0B / 4.1kB",
+                "pids": "3"
+            }
+        ]"#;
+
+        let stats: ContainerSample = source::parse_podman_stats(output).unwrap().remove(0).into();
+        assert_eq!(stats.id, "e4c5f1a2b3d4");
+        assert_eq!(stats.cpu_perc, 1.5);
+        assert_eq!((stats.mem_usage, stats.mem_limit), (12_300_000, 8_200_000_000));
+        assert_eq!((stats.block_read, stats.block_write), (0, 4_100));
+
+        // Nothing running
+        assert!(source::parse_podman_stats(b"").unwrap().is_empty());
+        assert!(source::parse_podman_stats(b"null\n").unwrap().is_empty());
+        assert!(source::parse_podman_stats(b"[{\"ID\": \"abc\"}]").is_err());
+    }
+
+    #[test]
+    fn engine_source_streams_matching_containers() {
+        static RUNNING: AtomicBool = AtomicBool::new(true);
+        let list = r#"[{"Id":"abc123","Names":["/web"]},{"Id":"def456","Names":["/db"]}]"#;
+        let socket = fake_daemon(
+            "source",
+            vec![
+                ("/containers/json", json_response(list)),
+                ("/containers/abc123/stats?stream=true", chunked_response(&[STATS])),
+            ]
+        );
+
+        let containers = Arc::new(Mutex::new(Vec::new()));
+        let (heartbeat_sender, heartbeat_receiver) = mpsc::channel();
+        let sink = Sink::new(containers.clone(), heartbeat_sender, &RUNNING, 60);
+        let mut source = EngineSource::new("Docker Engine API", EngineClient::new(socket), Filter::new(vec!["web".to_string()]));
+        let handle = thread::spawn(move || source.run(&sink));
+
+        // The fake stream ends right away, so the row is published and then marked as exited
+        thread::sleep(Duration::from_millis(300));
+        RUNNING.store(false, Ordering::SeqCst);
+        assert!(handle.join().unwrap().is_ok());
+        assert_eq!(heartbeat_receiver.try_iter().count(), 1);
+        let guard = containers.lock().unwrap();
+        assert_eq!(guard.len(), 1);
+        assert!(guard[0].exited.is_some());
+    }
+}
+
+#[cfg(test)]
+mod cgroup_tests {
+    use docker_stats::cgroup::{self, CgroupReader};
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+        time::{Duration, Instant}
+    };
+
+    const WEB: &str = "4f6f1b2a3c4d5e6f708192a3b4c5d6e7f8091a2b3c4d5e6f708192a3b4c5d6e7";
+    const DB: &str = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+
+    /// Creates an empty fixture hierarchy in the temp dir.
+    fn fixture(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("ds-cgroup-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn write(dir: &Path, files: &[(&str, &str)]) {
+        fs::create_dir_all(dir).unwrap();
+        for (file, content) in files {
+            fs::write(dir.join(file), content).unwrap();
+        }
+    }
+
+    #[test]
+    fn container_id() {
+        assert_eq!(cgroup::container_id(&format!("docker-{WEB}.scope")).as_deref(), Some(WEB));
+        assert_eq!(cgroup::container_id(&format!("cri-containerd-{WEB}.scope")).as_deref(), Some(WEB));
+        assert_eq!(cgroup::container_id(WEB).as_deref(), Some(WEB));
+
+        // Pitfalls
+        assert_eq!(cgroup::container_id("system.slice"), None);
+        assert_eq!(cgroup::container_id("docker-1234.scope"), None);
+        assert_eq!(cgroup::container_id(&format!("libpod-conmon-{WEB}.scope")), None);
+    }
+
+    #[test]
+    fn discover_container_cgroups() {
+        let root = fixture("discover");
+        write(&root.join(format!("system.slice/docker-{WEB}.scope/init")), &[]);
+        write(&root.join("system.slice/sshd.service"), &[]);
+        write(
+            &root.join(format!(
+                "kubepods.slice/kubepods-burstable.slice/kubepods-burstable-pod1.slice/cri-containerd-{DB}.scope"
+            )),
+            &[]
+        );
+
+        let found = cgroup::discover(&root);
+        let ids: Vec<&str> = found.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, vec![DB, WEB]);
+    }
+
+    #[test]
+    fn read_v2_stats() {
+        let root = fixture("v2");
+        write(&root, &[("cgroup.controllers", "cpu io memory pids\n")]);
+        let web = root.join(format!("system.slice/docker-{WEB}.scope"));
+        write(
+            &web,
+            &[
+                ("cpu.stat", "usage_usec 1000000\nuser_usec 800000\nsystem_usec 200000\n"),
+                ("memory.current", "3145728\n"),
+                ("memory.max", "8388608\n"),
+                ("memory.stat", "anon 2097152\nfile 1048576\ninactive_file 1048576\n"),
+                ("memory.events", "low 0\nhigh 0\nmax 4\noom 1\noom_kill 1\n"),
+                ("cpu.max", "50000 100000\n"),
+                ("memory.swap.current", "4096\n"),
+                (
+                    "io.stat",
+                    "8:0 rbytes=2000 wbytes=1000 rios=2 wios=1 dbytes=0 dios=0\n8:16 rbytes=0 wbytes=2000 rios=0 wios=3\n"
+                )
+            ]
+        );
+
+        let mut reader = CgroupReader::new(&root);
+        let start = Instant::now();
+        let first = reader.read_at(start);
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].name, &WEB[..12]);
+        assert_eq!(first[0].cpu_perc, 0.0);
+        assert_eq!((first[0].mem_usage, first[0].mem_limit), (2_097_152, 8_388_608));
+        assert_eq!(first[0].mem_perc, 25.0);
+        assert_eq!((first[0].block_read, first[0].block_write), (2_000, 3_000));
+        assert_eq!(first[0].oom_kills, Some(1));
+        assert_eq!(first[0].mem_limited, Some(true));
+        assert_eq!(first[0].cpu_limit, Some(0.5));
+        let breakdown = first[0].mem_breakdown.unwrap();
+        assert_eq!((breakdown.anon, breakdown.file, breakdown.swap), (2_097_152, 1_048_576, Some(4096)));
+
+        // Half a core over one second
+        write(&web, &[("cpu.stat", "usage_usec 1500000\n")]);
+        let second = reader.read_at(start + Duration::from_secs(1));
+        assert_eq!(second[0].cpu_perc, 50.0);
+    }
+
+    #[test]
+    fn names_from_docker_configs() {
+        let root = fixture("names");
+        write(&root, &[("cgroup.controllers", "cpu io memory pids\n")]);
+        for id in [WEB, DB] {
+            write(
+                &root.join(format!("system.slice/docker-{id}.scope")),
+                &[("cpu.stat", "usage_usec 0\n")]
+            );
+        }
+        let configs = root.join("containers");
+        write(&configs.join(WEB), &[("config.v2.json", r#"{"ID":"4f6f1b2a","Name":"/web"}"#)]);
+
+        // Without a readable config, the short ID stands in
+        let samples = CgroupReader::new(&root).with_configs(&configs).read();
+        let names: Vec<&str> = samples.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["0123456789ab", "web"]);
+    }
+
+    #[test]
+    fn read_v1_stats() {
+        let root = fixture("v1");
+        let cpuacct = root.join(format!("cpu,cpuacct/docker/{WEB}"));
+        write(
+            &cpuacct,
+            &[
+                ("cpuacct.usage", "2000000000\n"),
+                ("cpu.cfs_quota_us", "200000\n"),
+                ("cpu.cfs_period_us", "100000\n")
+            ]
+        );
+        write(
+            &root.join(format!("memory/docker/{WEB}")),
+            &[
+                ("memory.usage_in_bytes", "3145728\n"),
+                ("memory.limit_in_bytes", "8388608\n"),
+                ("memory.stat", "cache 1048576\nrss 2097152\ntotal_inactive_file 1048576\n")
+            ]
+        );
+        write(
+            &root.join(format!("blkio/docker/{WEB}")),
+            &[(
+                "blkio.throttle.io_service_bytes",
+                "8:0 Read 2000\n8:0 Write 1000\n8:0 Sync 3000\n8:0 Total 3000\n8:16 Write 2000\nTotal 5000\n"
+            )]
+        );
+
+        assert_eq!(cgroup::version(&root), cgroup::Version::V1);
+        assert!(CgroupReader::new(&root).readable());
+        assert!(!CgroupReader::new(fixture("empty")).readable());
+        assert!(!CgroupReader::new(root.join("missing")).readable());
+
+        let mut reader = CgroupReader::new(&root);
+        let start = Instant::now();
+        let first = reader.read_at(start);
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].id, WEB);
+        assert_eq!(first[0].cpu_limit, Some(2.0));
+        assert_eq!((first[0].mem_usage, first[0].mem_limit), (2_097_152, 8_388_608));
+        assert_eq!((first[0].block_read, first[0].block_write), (2_000, 3_000));
+
+        // A full core and a half over two seconds
+        write(&cpuacct, &[("cpuacct.usage", "5000000000\n")]);
+        let second = reader.read_at(start + Duration::from_secs(2));
+        assert_eq!(second[0].cpu_perc, 150.0);
+    }
+
+    #[test]
+    fn v1_unlimited_memory_uses_host_memory() {
+        let root = fixture("v1-unlimited");
+        write(&root.join(format!("cpuacct/docker/{WEB}")), &[("cpuacct.usage", "0\n")]);
+        write(
+            &root.join(format!("memory/docker/{WEB}")),
+            &[
+                ("memory.usage_in_bytes", "1024\n"),
+                ("memory.limit_in_bytes", "9223372036854771712\n")
+            ]
+        );
+
+        let stats = CgroupReader::new(&root).read_at(Instant::now());
+        assert!(stats[0].mem_limit < 9223372036854771712);
+        assert_eq!(stats[0].mem_limited, Some(false));
+        assert_eq!(stats[0].cpu_limit, None);
+    }
+}
+
+#[cfg(test)]
+mod state_tests {
+    use docker_stats::{
+        data::{ContainerSample, Metadata},
+        event::EventKind,
+        state::{ContainerState, History, Rates, STALE_AFTER}
+    };
+    use std::{
+        sync::LazyLock,
+        time::{Duration, Instant}
+    };
+
+    static START: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+    fn sample(net: (u64, u64), block: (u64, u64), after: Duration) -> ContainerSample {
+        let mut sample = ContainerSample::new("abc123", "web", 0.0, (0, 0), net, block);
+        sample.timestamp = *START + after;
+        sample
+    }
+
+    #[test]
+    fn rates_between_samples() {
+        let previous = sample((1_000, 500), (0, 0), Duration::ZERO);
+        let current = sample((5_000, 1_500), (2_000, 8_000), Duration::from_secs(2));
+
+        let rates = Rates::between(&previous, &current).unwrap();
+        assert_eq!(rates.net_rx, 2_000.0);
+        assert_eq!(rates.net_tx, 500.0);
+        assert_eq!(rates.block_read, 1_000.0);
+        assert_eq!(rates.block_write, 4_000.0);
+    }
+
+    #[test]
+    fn rates_over_repeated_samples() {
+        // The docker CLI prints every 500ms, the daemon refreshes the counters every second
+        let mut state = ContainerState::new(sample((0, 0), (0, 0), Duration::ZERO), 60);
+        let mut rates = Vec::new();
+        for tick in 1..=6u64 {
+            let counters = tick / 2 * 1_000_000;
+            state.update(sample((counters, 0), (0, 0), Duration::from_millis(tick * 500)));
+            rates.push(state.rates.net_rx);
+        }
+        assert_eq!(rates, [0.0, 1_000_000.0, 1_000_000.0, 1_000_000.0, 1_000_000.0, 1_000_000.0]);
+
+        // Counters that keep repeating do mean no traffic
+        state.update(sample((3_000_000, 0), (0, 0), Duration::from_millis(4_000)));
+        assert_eq!(state.rates.net_rx, 0.0);
+    }
+
+    #[test]
+    fn rates_after_counter_reset() {
+        // A restarted container starts counting from zero again
+        let previous = sample((5_000, 5_000), (5_000, 5_000), Duration::ZERO);
+        let current = sample((100, 0), (0, 0), Duration::from_secs(1));
+
+        assert_eq!(Rates::between(&previous, &current), Some(Rates::default()));
+    }
+
+    #[test]
+    fn history_window() {
+        let mut history = History::new(3);
+        (1..=5).for_each(|v| history.push(*START + Duration::from_secs(v), v as f64));
+
+        assert_eq!(history.values().collect::<Vec<_>>(), vec![3.0, 4.0, 5.0]);
+        assert_eq!(history.max(), 5.0);
+
+        // Disabled history keeps nothing
+        let mut disabled = History::new(0);
+        disabled.push(*START, 1.0);
+        assert_eq!(disabled.values().count(), 0);
+    }
+
+    #[test]
+    fn history_keeps_seconds_whatever_the_rate() {
+        // The docker CLI prints twice a second, the 60 seconds asked for are 120 samples
+        let mut state = ContainerState::new(sample((0, 0), (0, 0), Duration::ZERO), 60);
+        for tick in 1..=200u64 {
+            state.update(sample((0, 0), (0, 0), Duration::from_millis(tick * 500)));
+        }
+        assert_eq!(state.history.cpu.values().len(), 120);
+    }
+
+    #[test]
+    fn state_records_history() {
+        let mut state = ContainerState::new(sample((0, 0), (0, 0), Duration::ZERO), 2);
+        state.update(sample((1_000, 0), (0, 0), Duration::from_secs(1)));
+        state.update(sample((3_000, 0), (0, 0), Duration::from_secs(2)));
+
+        assert_eq!(state.history.net_rx.values().collect::<Vec<_>>(), vec![1_000.0, 2_000.0]);
+        assert_eq!(state.history.cpu.values().count(), 2);
+    }
+
+    #[test]
+    fn state_keeps_rates_without_elapsed_time() {
+        let mut state = ContainerState::new(sample((0, 0), (0, 0), Duration::ZERO), 60);
+        state.update(sample((1_000, 0), (0, 0), Duration::from_secs(1)));
+        assert_eq!(state.rates.net_rx, 1_000.0);
+
+        // Same timestamp, nothing to derive a rate from
+        state.update(sample((3_000, 0), (0, 0), Duration::from_secs(1)));
+        assert_eq!(state.rates.net_rx, 1_000.0);
+        assert_eq!(state.sample.net_rx, 3_000);
+    }
+
+    #[test]
+    fn state_goes_stale() {
+        let mut state = ContainerState::new(ContainerSample::new("abc123", "web", 0.0, (0, 0), (0, 0), (0, 0)), 60);
+        assert!(!state.is_stale());
+
+        // No samples for a while
+        state.sample.timestamp -= STALE_AFTER * 2;
+        assert!(state.is_stale());
+        assert_eq!(state.last_seen(), state.sample.timestamp);
+
+        // Exited containers count from when they stopped, until a sample brings them back
+        let exited = Instant::now();
+        state.exited = Some(exited);
+        assert_eq!(state.last_seen(), exited);
+        state.update(ContainerSample::new("abc123", "web", 0.0, (0, 0), (0, 0), (0, 0)));
+        assert!(!state.is_stale());
+    }
+
+    #[test]
+    fn counts_oom_kills_once() {
+        // The cgroup had counted two kills before the session started
+        let mut first = ContainerSample::new("abc123", "web", 0.0, (0, 0), (0, 0), (0, 0));
+        first.oom_kills = Some(2);
+        let mut state = ContainerState::new(first.clone(), 60);
+        assert_eq!(state.incidents.ooms(), 0);
+
+        // One kill, reported by the cgroup, the event stream and inspect alike
+        first.oom_kills = Some(3);
+        state.update(first);
+        state.record(EventKind::Oom);
+        let described = |oom_killed| Metadata {
+            started_at: Some(1_700_000_000),
+            oom_killed,
+            ..Metadata::default()
+        };
+        state.describe(described(false));
+        state.describe(described(true));
+        state.describe(described(true));
+        assert_eq!(state.incidents.ooms(), 1);
+    }
+
+    #[test]
+    fn ignores_oom_flags_from_before_the_session() {
+        let mut state = ContainerState::new(ContainerSample::new("abc123", "web", 0.0, (0, 0), (0, 0), (0, 0)), 60);
+        let described = |oom_killed| Metadata {
+            started_at: Some(1_700_000_000),
+            oom_killed,
+            ..Metadata::default()
+        };
+
+        // The last exit was an OOM kill before the session started
+        state.describe(Metadata::default());
+        state.describe(described(true));
+        assert_eq!(state.incidents.ooms(), 0);
+
+        // Cleared by a restart, then flagged again
+        state.describe(described(false));
+        state.describe(described(true));
+        assert_eq!(state.incidents.ooms(), 1);
+    }
+
+    #[test]
+    fn detects_crash_loops() {
+        let mut state = ContainerState::new(ContainerSample::new("abc123", "web", 0.0, (0, 0), (0, 0), (0, 0)), 60);
+        let described = |restarts| Metadata {
+            started_at: Some(1_700_000_000),
+            restarts,
+            ..Metadata::default()
+        };
+
+        // Restarts from before the session don't count
+        state.describe(described(7));
+        state.record(EventKind::Restart);
+        assert_eq!(state.incidents.restarts(), 1);
+        assert!(!state.is_crash_looping());
+
+        state.describe(described(10));
+        assert_eq!(state.incidents.restarts(), 3);
+        assert!(state.is_crash_looping());
+    }
+}
+
+#[cfg(test)]
+mod chart_tests {
+    use colored::Color;
+    use docker_stats::{
+        chart::{self, BrailleCanvas, Series},
+        data::ContainerSample,
+        event::EventKind,
+        state::{ContainerState, History, Marks}
+    };
+    use std::time::{Duration, Instant};
+
+    /// Strips the colour codes out of a rendered line.
+    fn plain(line: &str) -> String {
+        let mut plain = String::new();
+        let mut chars = line.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                chars.by_ref().find(|c| *c == 'm');
+            } else {
+                plain.push(c);
+            }
+        }
+        plain
+    }
+
+    #[test]
+    fn braille_dots() {
+        let mut canvas = BrailleCanvas::new(2, 1);
+        assert_eq!(canvas.char(0, 0), '⠀');
+
+        canvas.set(0, 0);
+        canvas.set(1, 3);
+        assert_eq!(canvas.char(0, 0), '⢁');
+
+        // Out of bounds dots are ignored
+        canvas.set(4, 0);
+        canvas.set(0, 4);
+        assert!(!canvas.is_set(1, 0));
+    }
+
+    #[test]
+    fn braille_lines() {
+        let mut canvas = BrailleCanvas::new(2, 1);
+        canvas.line((0, 3), (3, 0));
+        assert_eq!(canvas.char(0, 0), '⡠');
+        assert_eq!(canvas.char(1, 0), '⠊');
+    }
+
+    #[test]
+    fn line_chart_layout() {
+        let mut history = History::new(60);
+        let start = Instant::now();
+        (0..60).for_each(|v| history.push(start + Duration::from_secs(v), v as f64));
+
+        let series = [Series::new(&history, Color::Cyan)];
+        let lines = chart::line_chart(&series, &Marks::default(), 100.0, 40, 5, &|v| format!("{v:.0}%"));
+        let lines: Vec<String> = lines.iter().map(|l| plain(l)).collect();
+
+        // Five rows plus the time axis and the event marks, all as wide as requested
+        assert_eq!(lines.len(), 7);
+        assert!(lines.iter().all(|l| l.chars().count() == 40));
+
+        assert!(lines[0].trim_start().starts_with("100% ┤"));
+        assert!(lines[2].trim_start().starts_with("50% ┤"));
+        assert!(lines[4].trim_start().starts_with("0% ┤"));
+        assert!(lines[5].contains("-60s") && lines[5].contains("-30s") && lines[5].ends_with("now"));
+        assert!(lines[6].trim().is_empty());
+    }
+
+    #[test]
+    fn events_on_time_axis() {
+        // Two samples a second over the last 60 seconds, like the docker CLI prints them
+        let start = Instant::now();
+        let sample = |ms: u64| {
+            let mut sample = ContainerSample::new("abc123", "web", 0.0, (0, 0), (0, 0), (0, 0));
+            sample.timestamp = start + Duration::from_millis(ms);
+            sample
+        };
+        let mut state = ContainerState::new(sample(0), 60);
+        state.history.events.mark(start, EventKind::Start);
+        (1..=118).for_each(|tick| state.update(sample(tick * 500)));
+        state
+            .history
+            .events
+            .mark(start + Duration::from_secs(30), EventKind::Restart);
+        state
+            .history
+            .events
+            .mark(start + Duration::from_secs(70), EventKind::Oom);
+
+        let kinds: Vec<&EventKind> = state
+            .history
+            .events
+            .events()
+            .map(|(_, kind)| kind)
+            .collect();
+        assert_eq!(kinds, [&EventKind::Start, &EventKind::Restart, &EventKind::Oom]);
+
+        // The oldest sample lines up with the left end of the axis, and events sit where they happened in time
+        let series = [Series::new(&state.history.cpu, Color::Cyan)];
+        let lines = chart::line_chart(&series, &state.history.events, 100.0, 40, 5, &|v| format!("{v:.0}%"));
+        let axis = plain(&lines[5]);
+        let origin = axis.chars().position(|c| c == '└').unwrap() + 1;
+        let marks: Vec<char> = plain(&lines[6]).chars().skip(origin).collect();
+        assert_eq!(marks[0], '▶');
+        assert_eq!(marks.iter().position(|c| *c == '↻'), Some(14));
+        assert_eq!(marks.last(), Some(&'!'));
+
+        // The marks leave the axis labels alone
+        assert!(axis.trim_end().ends_with("─now"));
+        assert!(axis.contains("└-60s─"));
+    }
+}
+
+#[cfg(test)]
+mod tui_tests {
+    use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+    use docker_stats::tui::Action;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Event { Event::Key(KeyEvent::new(code, modifiers)) }
+
+    #[test]
+    fn keys_to_actions() {
+        let action = |code| Action::from_event(key(code, KeyModifiers::NONE));
+
+        assert_eq!(action(KeyCode::Up), Some(Action::Up));
+        assert_eq!(action(KeyCode::Char('k')), Some(Action::Up));
+        assert_eq!(action(KeyCode::Down), Some(Action::Down));
+        assert_eq!(action(KeyCode::Char('j')), Some(Action::Down));
+        assert_eq!(action(KeyCode::Enter), Some(Action::Open));
+        assert_eq!(action(KeyCode::Esc), Some(Action::Back));
+        assert_eq!(action(KeyCode::Char('s')), Some(Action::Sort));
+        assert_eq!(action(KeyCode::Char('r')), Some(Action::Reverse));
+        assert_eq!(action(KeyCode::Char('q')), Some(Action::Quit));
+        assert_eq!(action(KeyCode::Char('x')), None);
+    }
+
+    #[test]
+    fn ctrl_c_quits() {
+        assert_eq!(
+            Action::from_event(key(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Some(Action::Quit)
+        );
+        assert_eq!(Action::from_event(key(KeyCode::Char('j'), KeyModifiers::CONTROL)), None);
+    }
+
+    #[test]
+    fn releases_and_resizes() {
+        let release = KeyEvent::new_with_kind(KeyCode::Char('q'), KeyModifiers::NONE, KeyEventKind::Release);
+        assert_eq!(Action::from_event(Event::Key(release)), None);
+        assert_eq!(Action::from_event(Event::Resize(80, 24)), Some(Action::Redraw));
+    }
+}
+
+#[cfg(test)]
+mod sort_tests {
+    use docker_stats::{
+        data::ContainerSample,
+        sort::{Sort, SortKey},
+        state::{ContainerState, CpuMode}
+    };
+
+    fn state(id: &str, name: &str, cpu: f64, mem: u64) -> ContainerState {
+        ContainerState::new(ContainerSample::new(id, name, cpu, (mem, 1000), (0, 0), (0, 0)), 0)
+    }
+
+    fn names(states: &[ContainerState]) -> Vec<&str> { states.iter().map(|s| s.sample.name.as_str()).collect() }
+
+    #[test]
+    fn parse_and_cycle_keys() {
+        assert_eq!(SortKey::parse("mem"), Some(SortKey::Mem));
+        assert_eq!(SortKey::parse("disk"), None);
+        assert_eq!(SortKey::Cpu.next(), SortKey::Mem);
+        assert_eq!(SortKey::Id.next(), SortKey::Cpu);
+        assert_eq!(SortKey::Io.name(), "io");
+    }
+
+    #[test]
+    fn metrics_sort_busiest_first() {
+        let mut states = vec![
+            state("1", "db", 5.0, 300),
+            state("2", "api", 50.0, 100),
+            state("3", "web", 20.0, 200),
+        ];
+
+        Sort::new(SortKey::Cpu, false).apply(&mut states);
+        assert_eq!(names(&states), ["api", "web", "db"]);
+
+        Sort::new(SortKey::Mem, false).apply(&mut states);
+        assert_eq!(names(&states), ["db", "web", "api"]);
+
+        Sort::new(SortKey::Cpu, true).apply(&mut states);
+        assert_eq!(names(&states), ["db", "web", "api"]);
+        assert!(!Sort::new(SortKey::Cpu, true).descending());
+    }
+
+    #[test]
+    fn names_sort_alphabetically() {
+        let mut states = vec![state("b", "web", 0.0, 0), state("c", "api", 0.0, 0), state("a", "db", 0.0, 0)];
+
+        Sort::new(SortKey::Name, false).apply(&mut states);
+        assert_eq!(names(&states), ["api", "db", "web"]);
+
+        Sort::new(SortKey::Id, false).apply(&mut states);
+        assert_eq!(names(&states), ["db", "web", "api"]);
+
+        Sort::new(SortKey::Name, true).apply(&mut states);
+        assert_eq!(names(&states), ["web", "db", "api"]);
+    }
+
+    #[test]
+    fn ties_keep_a_stable_order() {
+        let mut states = vec![state("2", "web", 10.0, 0), state("3", "api", 10.0, 0), state("1", "web", 10.0, 0)];
+
+        Sort::new(SortKey::Cpu, false).apply(&mut states);
+        let ids: Vec<&str> = states.iter().map(|s| s.id()).collect();
+        assert_eq!(ids, ["3", "1", "2"]);
+    }
+
+    #[test]
+    fn cpu_sorts_by_the_cpu_mode() {
+        // Two cores of a four core quota is lighter than one core of a single core quota
+        let mut states = vec![state("1", "db", 200.0, 0), state("2", "api", 100.0, 0)];
+        states[0].metadata.cpu_limit = Some(4.0);
+        states[1].metadata.cpu_limit = Some(1.0);
+
+        Sort::new(SortKey::Cpu, false).apply(&mut states);
+        assert_eq!(names(&states), ["db", "api"]);
+
+        Sort::new(SortKey::Cpu, false)
+            .with_cpu_mode(CpuMode::Quota)
+            .apply(&mut states);
+        assert_eq!(names(&states), ["api", "db"]);
+    }
+}
+
+#[cfg(test)]
+mod filter_tests {
+    use docker_stats::{
+        data::ContainerSample,
+        engine::ContainerSummary,
+        filter::{Filter, Label, Pattern}
+    };
+
+    fn summary(name: &str, image: &str, labels: &str) -> ContainerSummary {
+        let json = format!(r#"{{"Id":"abc123","Names":["/{name}"],"Image":"{image}","ImageID":"sha256:f00d","Labels":{labels}}}"#);
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn glob_patterns() {
+        let pattern = Pattern::parse("api-*").unwrap();
+        assert!(pattern.matches("api-1"));
+        assert!(pattern.matches("api-"));
+        assert!(!pattern.matches("web-api-1"));
+
+        let pattern = Pattern::parse("db?.local").unwrap();
+        assert!(pattern.matches("db1.local"));
+        assert!(!pattern.matches("db1xlocal"));
+    }
+
+    #[test]
+    fn regex_patterns() {
+        let pattern = Pattern::parse(r"/^api-\d+$/").unwrap();
+        assert!(pattern.matches("api-12"));
+        assert!(!pattern.matches("api-x"));
+
+        // Unanchored regexes match anywhere in the name
+        assert!(Pattern::parse("/worker/")
+            .unwrap()
+            .matches("queue-worker-2"));
+        assert!(Pattern::parse("/(/").is_err());
+    }
+
+    #[test]
+    fn labels() {
+        assert_eq!(
+            Label::parse("tier=web").unwrap(),
+            Label {
+                key: "tier".to_string(),
+                value: Some("web".to_string())
+            }
+        );
+        assert!(Label::parse("=web").is_err());
+
+        let web = summary("web", "nginx", r#"{"tier":"web","team":""}"#);
+        let labels = web.labels.clone().unwrap();
+        assert!(Label::parse("tier=web").unwrap().matches(&labels));
+        assert!(Label::parse("tier").unwrap().matches(&labels));
+        assert!(Label::parse("team=").unwrap().matches(&labels));
+        assert!(!Label::parse("tier=db").unwrap().matches(&labels));
+        assert!(!Label::parse("owner").unwrap().matches(&labels));
+    }
+
+    #[test]
+    fn names_add_up_and_exclusions_win() {
+        let filter = Filter {
+            patterns: vec![Pattern::parse("api-*").unwrap()],
+            excludes: vec![Pattern::parse("*-canary").unwrap()],
+            ..Filter::new(vec!["db".to_string()])
+        };
+
+        assert!(filter.matches(&summary("db", "postgres", "null")));
+        assert!(filter.matches(&summary("api-1", "api", "null")));
+        assert!(!filter.matches(&summary("api-canary", "api", "null")));
+        assert!(!filter.matches(&summary("web", "nginx", "null")));
+        assert!(Filter::default().matches(&summary("web", "nginx", "null")));
+    }
+
+    #[test]
+    fn images_and_labels_narrow_down() {
+        let filter = Filter {
+            labels: vec![Label::parse("tier=web").unwrap()],
+            images: vec!["nginx".to_string(), "registry:5000/api:2".to_string()],
+            ..Filter::default()
+        };
+        assert_eq!(filter.metadata_flags(), ["--label", "--image"]);
+
+        assert!(filter.matches(&summary("web", "nginx:1.25", r#"{"tier":"web"}"#)));
+        assert!(filter.matches(&summary("web", "registry:5000/api:2", r#"{"tier":"web"}"#)));
+        assert!(!filter.matches(&summary("web", "registry:5000/api:3", r#"{"tier":"web"}"#)));
+        assert!(!filter.matches(&summary("web", "nginx", r#"{"tier":"db"}"#)));
+        assert!(!filter.matches(&summary("web", "nginx", "null")));
+
+        // Images can also be referenced by ID
+        let filter = Filter {
+            images: vec!["f00d".to_string()],
+            ..Filter::default()
+        };
+        assert!(filter.matches(&summary("web", "nginx", "{}")));
+    }
+
+    #[test]
+    fn samples_match_by_name() {
+        let sample = ContainerSample::new("abc123", "api-1", 0.0, (0, 0), (0, 0), (0, 0));
+        let filter = |containers: &[&str], pattern: &str| Filter {
+            patterns: vec![Pattern::parse(pattern).unwrap()],
+            ..Filter::new(containers.iter().map(|c| c.to_string()).collect())
+        };
+
+        assert!(filter(&[], "api-?").matches_sample(&sample));
+        assert!(filter(&["abc"], "web").matches_sample(&sample));
+        assert!(!filter(&["db"], "web").matches_sample(&sample));
+    }
+}
+
+#[cfg(test)]
+mod group_tests {
+    use docker_stats::{
+        data::ContainerSample,
+        group::{self, GroupBy, COMPOSE_NUMBER, COMPOSE_PROJECT, COMPOSE_SERVICE},
+        state::{ContainerState, CpuMode}
+    };
+
+    fn state(name: &str, cpu: f64, labels: &[(&str, &str)]) -> ContainerState {
+        let mut state = ContainerState::new(ContainerSample::new(name, name, cpu, (100, 1000), (0, 0), (0, 0)), 0);
+        state.metadata.labels = labels
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        state
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(GroupBy::parse("compose"), Ok(GroupBy::Compose));
+        assert_eq!(GroupBy::parse("image"), Ok(GroupBy::Image));
+        assert_eq!(GroupBy::parse("label:team"), Ok(GroupBy::Label("team".to_string())));
+        assert!(GroupBy::parse("label:").is_err());
+        assert!(GroupBy::parse("team").is_err());
+    }
+
+    #[test]
+    fn labels_and_images() {
+        let mut states = [
+            state("a", 1.0, &[("team", "red")]),
+            state("b", 2.0, &[("team", "blue")]),
+            state("c", 3.0, &[])
+        ];
+        states[0].metadata.image = "nginx:1.27".to_string();
+        states[1].metadata.image = "nginx:1.27".to_string();
+
+        let by_team = GroupBy::Label("team".to_string());
+        let groups = group::group(&by_team, &states);
+        let keys: Vec<Option<&str>> = groups.iter().map(|g| g.key.as_deref()).collect();
+        assert_eq!(keys, [Some("blue"), Some("red"), None]);
+        assert_eq!(by_team.ungrouped(), "no team label");
+        assert_eq!(by_team.title(&states[0]), None);
+
+        let groups = group::group(&GroupBy::Image, &states);
+        let keys: Vec<Option<&str>> = groups.iter().map(|g| g.key.as_deref()).collect();
+        assert_eq!(keys, [Some("nginx:1.27"), None]);
+        assert_eq!(groups[0].totals(CpuMode::Core).cpu_perc, 3.0);
+    }
+
+    #[test]
+    fn compose_projects() {
+        let states = [
+            state("web", 1.0, &[]),
+            state("shop-db-1", 2.0, &[(COMPOSE_PROJECT, "shop"), (COMPOSE_SERVICE, "db")]),
+            state("blog-api-1", 3.0, &[(COMPOSE_PROJECT, "blog"), (COMPOSE_SERVICE, "api")]),
+            state(
+                "shop-api-2",
+                4.0,
+                &[(COMPOSE_PROJECT, "shop"), (COMPOSE_SERVICE, "api"), (COMPOSE_NUMBER, "2")]
+            )
+        ];
+
+        // Sorted by project, the containers without one last
+        let groups = group::group(&GroupBy::Compose, &states);
+        let keys: Vec<Option<&str>> = groups.iter().map(|g| g.key.as_deref()).collect();
+        assert_eq!(keys, [Some("blog"), Some("shop"), None]);
+
+        // Containers keep their order within a group
+        let shop: Vec<&str> = groups[1]
+            .containers
+            .iter()
+            .map(|s| s.sample.name.as_str())
+            .collect();
+        assert_eq!(shop, ["shop-db-1", "shop-api-2"]);
+
+        let totals = groups[1].totals(CpuMode::Core);
+        assert_eq!(totals.cpu_perc, 6.0);
+        assert_eq!(totals.mem_usage, 200);
+
+        // Against the summed limits in quota mode, as if the project were one container
+        let mut states = states;
+        states[1].metadata.cpu_limit = Some(1.0);
+        states[3].metadata.cpu_limit = Some(3.0);
+        let groups = group::group(&GroupBy::Compose, &states);
+        assert_eq!(groups[1].totals(CpuMode::Quota).cpu_perc, 1.5);
+
+        // Rows go by their service name
+        assert_eq!(GroupBy::Compose.title(&states[0]), None);
+        assert_eq!(GroupBy::Compose.title(&states[1]).as_deref(), Some("db"));
+        assert_eq!(GroupBy::Compose.title(&states[3]).as_deref(), Some("api #2"));
+    }
+}
+
+#[cfg(test)]
+mod event_tests {
+    use super::engine_tests::{chunked_response, fake_daemon};
+    use docker_stats::{
+        data::ContainerSample,
+        engine::EngineClient,
+        event::{Event, EventKind, EventLog},
+        filter::{Filter, Pattern},
+        source::Sink
+    };
+    use std::{
+        collections::HashMap,
+        sync::{atomic::AtomicBool, mpsc, Arc, Mutex}
+    };
+
+    #[test]
+    fn parse_actions() {
+        let attributes: HashMap<String, String> = [("exitCode".to_string(), "137".to_string())].into();
+        let none = HashMap::new();
+
+        assert_eq!(EventKind::parse("start", &none), Some(EventKind::Start));
+        assert_eq!(EventKind::parse("die", &attributes), Some(EventKind::Die(137)));
+        assert_eq!(EventKind::parse("oom", &none), Some(EventKind::Oom));
+        assert_eq!(
+            EventKind::parse("health_status: unhealthy", &none),
+            Some(EventKind::Health("unhealthy".to_string()))
+        );
+        assert_eq!(EventKind::parse("exec_start: sh", &none), None);
+
+        // Podman has the status as an attribute
+        let podman: HashMap<String, String> = [("health_status".to_string(), "healthy".to_string())].into();
+        assert_eq!(
+            EventKind::parse("health_status", &podman),
+            Some(EventKind::Health("healthy".to_string()))
+        );
+        assert_eq!(EventKind::Die(137).to_string(), "died (exit 137)");
+    }
+
+    #[test]
+    fn log_keeps_the_latest() {
+        let mut log = EventLog::new(2);
+        for id in ["a", "b", "c"] {
+            log.push(Event {
+                at: 0,
+                id: id.to_string(),
+                name: id.to_string(),
+                kind: EventKind::Start
+            });
+        }
+
+        let ids: Vec<&str> = log.events().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["b", "c"]);
+    }
+
+    #[test]
+    fn stream_events() {
+        let die = r#"{"Type":"container","Action":"die","Actor":{"ID":"abc123","Attributes":{"name":"web","image":"nginx","exitCode":"1","tier":"front"}},"time":1714566615}"#;
+        let pull = r#"{"Type":"image","Action":"pull","Actor":{"ID":"nginx","Attributes":{}},"time":1714566616}"#;
+        let socket = fake_daemon(
+            "events",
+            vec![(
+                "/events?filters=%7B%22type%22%3A%5B%22container%22%5D%7D",
+                chunked_response(&[die, "\n", pull, "\n"])
+            )]
+        );
+
+        let messages: Vec<_> = EngineClient::new(socket)
+            .stream_events()
+            .unwrap()
+            .map(|m| m.unwrap())
+            .collect();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(
+            messages[0].event(),
+            Some(Event {
+                at: 1714566615,
+                id: "abc123".to_string(),
+                name: "web".to_string(),
+                kind: EventKind::Die(1)
+            })
+        );
+        assert_eq!(messages[1].event(), None);
+
+        // Filtered like the listing, with the attributes standing in for labels
+        let summary = messages[0].summary();
+        assert!(Filter::new(vec!["web".to_string()]).matches(&summary));
+        let excluded = Filter {
+            excludes: vec![Pattern::parse("w*").unwrap()],
+            ..Filter::default()
+        };
+        assert!(!excluded.matches(&summary));
+    }
+
+    #[test]
+    fn sink_records_events() {
+        static RUNNING: AtomicBool = AtomicBool::new(true);
+        let containers = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::new(Mutex::new(EventLog::default()));
+        let (heartbeat_sender, _heartbeat_receiver) = mpsc::channel();
+        let sink = Sink::new(containers.clone(), heartbeat_sender, &RUNNING, 60).with_events(log.clone());
+
+        sink.publish(ContainerSample::new("abc123", "web", 0.0, (0, 0), (0, 0), (0, 0)));
+        sink.record(Event {
+            at: 0,
+            id: "abc123".to_string(),
+            name: "web".to_string(),
+            kind: EventKind::Die(1)
+        });
+
+        // Marked on the history, and the container is known to be gone before its stats stop
+        let guard = containers.lock().unwrap();
+        assert_eq!(
+            guard[0]
+                .history
+                .events
+                .events()
+                .last()
+                .map(|(_, kind)| kind),
+            Some(&EventKind::Die(1))
+        );
+        assert!(guard[0].exited.is_some());
+        assert_eq!(log.lock().unwrap().events().len(), 1);
+    }
+}